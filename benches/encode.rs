@@ -0,0 +1,56 @@
+//! Compares fd_bs58 with the bs58 crate at encoding 32-byte pubkeys, the
+//! one encoding the grind loop does per candidate (see
+//! `matcher::Candidate::encoded`). Run with `cargo bench --bench encode`.
+//!
+//! A plain timing loop rather than criterion, which is heavier than this
+//! comparison needs; each round prints ns per encoding.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use sha2::{Digest, Sha256};
+use vanity::base58::Aligned;
+
+/// Encodings timed per library per round
+const ENCODINGS: u32 = 2_000_000;
+
+/// Distinct inputs, so nothing is encoding the same bytes over and over
+const INPUTS: usize = 4096;
+
+fn main() {
+    // Hashes, like the candidates the grinder encodes, and aligned like
+    // them too since fd_bs58 needs it
+    let inputs: Vec<Aligned> = (0..INPUTS)
+        .map(|i| Aligned(Sha256::digest(i.to_le_bytes()).into()))
+        .collect();
+    for input in &inputs {
+        assert_eq!(fd_bs58::encode_32(input), bs58::encode(input).into_string());
+    }
+
+    for round in 1..=3 {
+        let fd = time(|i| {
+            black_box(fd_bs58::encode_32(black_box(&inputs[i % INPUTS])));
+        });
+        let mut encoded = String::with_capacity(44);
+        let bs58 = time(|i| {
+            encoded.clear();
+            bs58::encode(black_box(&inputs[i % INPUTS]))
+                .onto(&mut encoded)
+                .unwrap();
+            black_box(&encoded);
+        });
+        println!(
+            "round {round}: fd_bs58 {fd:.1} ns, bs58 {bs58:.1} ns ({:.1}x)",
+            bs58 / fd
+        );
+    }
+}
+
+/// Mean ns per call of `encode` over [`ENCODINGS`] calls
+fn time(mut encode: impl FnMut(usize)) -> f64 {
+    let start = Instant::now();
+    for i in 0..ENCODINGS {
+        encode(i as usize);
+    }
+    start.elapsed().as_nanos() as f64 / ENCODINGS as f64
+}
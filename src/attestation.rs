@@ -0,0 +1,35 @@
+use solana_pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+/// Leads every attestation message, so a signature over one can't pass
+/// for a signature over anything else the key signs
+const DOMAIN: &str = "vanity-attestation:v1";
+
+/// The bytes a server signs to attest that it generated `address` from
+/// `seed` under `base` and `owner` at `timestamp` (unix seconds): each
+/// field on its own line after [`DOMAIN`]. Generated seeds are printable
+/// ASCII, so the fields can't run together.
+pub fn attestation_message(
+    address: &Pubkey,
+    seed: &str,
+    base: &Pubkey,
+    owner: &Pubkey,
+    timestamp: u64,
+) -> Vec<u8> {
+    format!("{DOMAIN}\n{address}\n{seed}\n{base}\n{owner}\n{timestamp}").into_bytes()
+}
+
+/// Whether `signature` is `server_pubkey`'s signature over the
+/// [`attestation_message`] for these fields
+pub fn verify_attestation(
+    address: &Pubkey,
+    seed: &str,
+    base: &Pubkey,
+    owner: &Pubkey,
+    timestamp: u64,
+    signature: &Signature,
+    server_pubkey: &Pubkey,
+) -> bool {
+    let message = attestation_message(address, seed, base, owner, timestamp);
+    signature.verify(server_pubkey.as_ref(), &message)
+}
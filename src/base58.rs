@@ -0,0 +1,280 @@
+/// The base58 alphabet used by `fd_bs58` (Bitcoin ordering). Note the
+/// absence of 0, O, I and l, which are too easily confused.
+pub const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Length of the longest encoded pubkey
+pub const MAX_ADDRESS_LEN: usize = 44;
+
+/// Membership table for the ascii range, built (and checked) at compile time
+const IS_BASE58: [bool; 128] = {
+    let alphabet = BASE58_ALPHABET.as_bytes();
+    assert!(alphabet.len() == 58, "base58 alphabet must have 58 chars");
+
+    let mut table = [false; 128];
+    let mut i = 0;
+    while i < alphabet.len() {
+        let c = alphabet[i] as usize;
+        assert!(c < 128, "base58 alphabet must be ascii");
+        assert!(!table[c], "base58 alphabet contains a duplicate");
+        table[c] = true;
+        i += 1;
+    }
+
+    // The whole point of base58
+    assert!(!table[b'0' as usize]);
+    assert!(!table[b'O' as usize]);
+    assert!(!table[b'I' as usize]);
+    assert!(!table[b'l' as usize]);
+
+    table
+};
+
+/// Whether `c` is a valid base58 character
+pub const fn is_base58_char(c: char) -> bool {
+    c.is_ascii() && IS_BASE58[c as usize]
+}
+
+/// A pubkey's bytes as `fd_bs58::encode_32` must be given them: it reads
+/// its input as u32s, which is only sound 4-byte aligned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(align(4))]
+pub struct Aligned(pub [u8; 32]);
+
+impl AsRef<[u8]> for Aligned {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Most case variants of a prefix that [`PrefixFilter`] will enumerate
+const MAX_PREFIX_VARIANTS: usize = 256;
+
+/// Little endian 64 bit limbs. Wider than a pubkey so (prefix + 1) * 58^k
+/// doesn't overflow for any k we need.
+type Wide = [u64; 5];
+
+/// Rejects pubkeys whose base58 encoding can't start with a prefix without
+/// doing the encoding, by precomputing which 256 bit integers encode to
+/// something starting with the prefix.
+///
+/// Leading 1s are leading zero bytes. Past those, an integer x whose
+/// digits start with P (k digits) satisfies P * 58^j <= x < (P + 1) * 58^j
+/// for some j, so the prefix is a union of ranges over the encoded lengths.
+/// Case-insensitive prefixes have a set of ranges per case variant.
+///
+/// Candidates that pass still need the usual string check: the filter may
+/// only look at the first few chars of a prefix, but it never rejects a
+/// pubkey that matches.
+#[derive(Clone, Debug)]
+pub struct PrefixFilter {
+    /// Sorted, disjoint [lo, hi) ranges of acceptable pubkeys
+    ranges: Vec<(Wide, Wide)>,
+}
+
+impl PrefixFilter {
+    /// Builds a filter over the first `max_chars` chars (after any leading
+    /// 1s) of an already validated and lowercased `prefix`. Returns None
+    /// when there is nothing worth filtering on.
+    pub fn new(prefix: &str, case_insensitive: bool, max_chars: usize) -> Option<PrefixFilter> {
+        let ones = prefix.chars().take_while(|&c| c == '1').count().min(32);
+
+        // Digits each of the leading chars may take, limited so the case
+        // variants stay enumerable
+        let mut digits: Vec<Vec<u64>> = Vec::new();
+        let mut variants = 1;
+        for c in prefix[ones..].chars().take(max_chars) {
+            let alternatives = matching_digits(c, case_insensitive);
+            if variants * alternatives.len() > MAX_PREFIX_VARIANTS {
+                break;
+            }
+            variants *= alternatives.len();
+            digits.push(alternatives);
+        }
+        if ones == 0 && digits.is_empty() {
+            return None;
+        }
+
+        // Exactly `ones` leading zero bytes if anything follows them
+        let width = 32 - ones as u32;
+        let max = pow2(8 * width);
+        let min = match (digits.is_empty(), width) {
+            (true, _) => [0; 5],
+            // Nothing can follow 32 zero bytes
+            (false, 0) => return Some(PrefixFilter { ranges: Vec::new() }),
+            (false, width) => pow2(8 * (width - 1)),
+        };
+
+        let mut ranges = Vec::new();
+        if digits.is_empty() {
+            ranges.push((min, max));
+        }
+        for variant in cartesian(&digits) {
+            // P and P + 1 for this variant
+            let mut lo = [0; 5];
+            for &digit in &variant {
+                mul_add(&mut lo, 58, digit);
+            }
+            let mut hi = lo;
+            mul_add(&mut hi, 1, 1);
+
+            // Scale up through every possible encoded length
+            while cmp(&lo, &max).is_lt() {
+                let clamped = (max_wide(lo, min), min_wide(hi, max));
+                if cmp(&clamped.0, &clamped.1).is_lt() {
+                    ranges.push(clamped);
+                }
+                mul_add(&mut lo, 58, 0);
+                mul_add(&mut hi, 58, 0);
+            }
+        }
+
+        // Sort and merge so lookups can binary search
+        ranges.sort_by(|a, b| cmp(&a.0, &b.0));
+        let mut merged: Vec<(Wide, Wide)> = Vec::with_capacity(ranges.len());
+        for (lo, hi) in ranges {
+            match merged.last_mut() {
+                Some(last) if !cmp(&last.1, &lo).is_lt() => last.1 = max_wide(last.1, hi),
+                _ => merged.push((lo, hi)),
+            }
+        }
+
+        Some(PrefixFilter { ranges: merged })
+    }
+
+    /// False only if `pubkey` certainly doesn't encode to the prefix
+    #[inline]
+    pub fn may_match(&self, pubkey: &[u8; 32]) -> bool {
+        let mut x = [0; 5];
+        for (i, limb) in x.iter_mut().take(4).enumerate() {
+            let end = 32 - 8 * i;
+            *limb = u64::from_be_bytes(pubkey[end - 8..end].try_into().unwrap());
+        }
+
+        let after = self.ranges.partition_point(|(lo, _)| !cmp(lo, &x).is_gt());
+        after > 0 && cmp(&x, &self.ranges[after - 1].1).is_lt()
+    }
+}
+
+/// Base58 digits that compare equal to `c`, folding case like
+/// maybe_bs58_aware_lowercase (which never folds L)
+fn matching_digits(c: char, case_insensitive: bool) -> Vec<u64> {
+    let fold = |c: char| {
+        if case_insensitive && c != 'L' {
+            c.to_ascii_lowercase()
+        } else {
+            c
+        }
+    };
+    BASE58_ALPHABET
+        .chars()
+        .enumerate()
+        .filter(|&(_, a)| fold(a) == fold(c))
+        .map(|(digit, _)| digit as u64)
+        .collect()
+}
+
+/// Every combination picking one digit per position
+fn cartesian(digits: &[Vec<u64>]) -> Vec<Vec<u64>> {
+    if digits.is_empty() {
+        return Vec::new();
+    }
+    digits.iter().fold(vec![Vec::new()], |acc, alternatives| {
+        acc.iter()
+            .flat_map(|prefix| {
+                alternatives.iter().map(move |&digit| {
+                    let mut next = prefix.clone();
+                    next.push(digit);
+                    next
+                })
+            })
+            .collect()
+    })
+}
+
+/// x = x * m + a
+fn mul_add(x: &mut Wide, m: u64, a: u64) {
+    let mut carry = a as u128;
+    for limb in x.iter_mut() {
+        let v = *limb as u128 * m as u128 + carry;
+        *limb = v as u64;
+        carry = v >> 64;
+    }
+}
+
+fn pow2(bits: u32) -> Wide {
+    let mut x = [0; 5];
+    x[bits as usize / 64] = 1 << (bits % 64);
+    x
+}
+
+fn cmp(a: &Wide, b: &Wide) -> std::cmp::Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+fn min_wide(a: Wide, b: Wide) -> Wide {
+    if cmp(&a, &b).is_le() {
+        a
+    } else {
+        b
+    }
+}
+
+fn max_wide(a: Wide, b: Wide) -> Wide {
+    if cmp(&a, &b).is_ge() {
+        a
+    } else {
+        b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alphabet_is_bitcoins() {
+        // A single byte below 58 encodes to its own digit
+        let bitcoin: String = (0..58u8)
+            .map(|digit| {
+                bs58::encode([digit])
+                    .with_alphabet(bs58::Alphabet::BITCOIN)
+                    .into_string()
+            })
+            .collect();
+        assert_eq!(BASE58_ALPHABET, bitcoin);
+
+        for c in (0..=255u8).map(char::from) {
+            assert_eq!(is_base58_char(c), bitcoin.contains(c), "{c:?}");
+        }
+        for c in ['0', 'O', 'I', 'l'] {
+            assert!(!is_base58_char(c), "{c:?}");
+        }
+    }
+
+    #[test]
+    fn prefix_filters_never_reject_a_match() {
+        use crate::maybe_bs58_aware_lowercase;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(109);
+        for _ in 0..10_000 {
+            let mut pubkey: [u8; 32] = rng.gen();
+            // Some leading zero bytes, for the leading 1s
+            let zeros = if rng.gen_ratio(1, 8) { rng.gen_range(1..=3) } else { 0 };
+            pubkey[..zeros].fill(0);
+            let address = bs58::encode(pubkey).into_string();
+
+            let len = rng.gen_range(1..=zeros + 6);
+            let case_insensitive = rng.gen();
+            let prefix = maybe_bs58_aware_lowercase(&address[..len], case_insensitive);
+            let max_chars = rng.gen_range(1..=8);
+            if let Some(filter) = PrefixFilter::new(&prefix, case_insensitive, max_chars) {
+                assert!(
+                    filter.may_match(&pubkey),
+                    "{address} for {prefix:?}, case insensitive {case_insensitive}, \
+                     {max_chars} chars"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// A map of at most `capacity` entries that evicts whichever was used
+/// least recently to make room. Holds nothing if `capacity` is 0.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    /// Each value with when it was last used
+    entries: HashMap<K, (V, u64)>,
+    /// Keys by when they were last used, oldest first
+    order: BTreeMap<u64, K>,
+    clock: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> LruCache<K, V> {
+        LruCache {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// The value for `key`, which then counts as just used
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        self.clock += 1;
+        let (value, used) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        *used = self.clock;
+        self.order.insert(self.clock, key.clone());
+        Some(value.clone())
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+        if let Some((_, used)) = self.entries.insert(key.clone(), (value, self.clock)) {
+            self.order.remove(&used);
+        } else if self.entries.len() > self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.insert(self.clock, key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        cache.insert(3, "c");
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some("b"));
+        assert_eq!(cache.get(&3), Some("c"));
+
+        // Replacing a value doesn't evict anything, but does count as a use
+        cache.insert(2, "B");
+        cache.insert(4, "d");
+        assert_eq!(cache.get(&3), None);
+        assert_eq!(cache.get(&2), Some("B"));
+        assert_eq!(cache.get(&4), Some("d"));
+    }
+
+    #[test]
+    fn get_counts_as_a_use() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(3, "c");
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.get(&3), Some("c"));
+
+        // Missing keys don't disturb the order
+        assert_eq!(cache.get(&5), None);
+        cache.insert(4, "d");
+        assert_eq!(cache.get(&1), None);
+    }
+
+    #[test]
+    fn tiny_capacities() {
+        let mut cache = LruCache::new(0);
+        cache.insert(1, "a");
+        assert_eq!(cache.get(&1), None);
+
+        let mut cache = LruCache::new(1);
+        cache.insert(1, "a");
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(2, "b");
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some("b"));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::grind::GrindError;
+
+/// Look-alike letter sequences, replaced after folding single chars
+const SEQUENCES: [(&str, &str); 2] = [("rn", "m"), ("vv", "w")];
+
+/// Folds `s` so that strings which look alike at a glance come out the
+/// same: lowercased, with digits folded into the letters they resemble
+/// (e.g. 0 → o, 1 → i, 5 → s) and sequences like "rn" into "m". Letters
+/// base58 lacks fold too, so "PayPal" and "PayPa1" both become "paypai".
+pub fn fold_confusables(s: &str) -> String {
+    let folded: String = s
+        .chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            '0' => 'o',
+            '1' | 'l' | 'j' => 'i',
+            '2' => 'z',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '6' | '8' => 'b',
+            '7' => 't',
+            '9' => 'g',
+            c => c,
+        })
+        .collect();
+    SEQUENCES
+        .iter()
+        .fold(folded, |folded, (from, to)| folded.replace(from, to))
+}
+
+/// Look-alike patterns an address must not contain, compared after
+/// [`fold_confusables`] on both sides
+#[derive(Clone, Debug, Default)]
+pub struct Blocklist {
+    /// Folded already
+    patterns: Vec<String>,
+}
+
+impl Blocklist {
+    /// Validates `patterns`, which must be non-empty and ascii letters or
+    /// digits, though not necessarily base58
+    pub fn new(patterns: &[String]) -> Result<Blocklist, GrindError> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                if pattern.is_empty() || !pattern.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(GrindError::InvalidLookalike(pattern.clone()));
+                }
+                Ok(fold_confusables(pattern))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Blocklist { patterns })
+    }
+
+    /// Whether `address` looks like it contains any of the patterns
+    pub fn is_blocked(&self, address: &str) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let folded = fold_confusables(address);
+        self.patterns.iter().any(|pattern| folded.contains(pattern.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocklist(patterns: &[&str]) -> Blocklist {
+        let patterns: Vec<_> = patterns.iter().map(|pattern| pattern.to_string()).collect();
+        Blocklist::new(&patterns).unwrap()
+    }
+
+    #[test]
+    fn look_alikes_are_blocked() {
+        let blocklist = blocklist(&["PayPal", "Amazon"]);
+        for address in [
+            "xxPayPa1xx",
+            "xxpaypalxx",
+            "xxPAYPA1xx",
+            "xxPayPaixx",
+            "xxP4yP41xx",
+            "xxArnaz0nxx",
+            "xxAmazonxx",
+        ] {
+            assert!(blocklist.is_blocked(address), "{address}");
+        }
+        for address in ["xxPayPxx", "xxPayMalxx", "xxAmazxx", "xxArnazxnxx"] {
+            assert!(!blocklist.is_blocked(address), "{address}");
+        }
+        assert!(!Blocklist::default().is_blocked("xxPayPa1xx"));
+    }
+
+    #[test]
+    fn folding() {
+        assert_eq!(fold_confusables("PayPal"), "paypai");
+        assert_eq!(fold_confusables("PayPa1"), "paypai");
+        assert_eq!(fold_confusables("rnvv"), "mw");
+        assert_eq!(fold_confusables("0123456789"), "oizeasbtbg");
+    }
+
+    #[test]
+    fn patterns_must_be_ascii_alphanumeric() {
+        for pattern in ["", "Pay Pal", "Pay-Pal", "Pαypal"] {
+            assert!(
+                matches!(
+                    Blocklist::new(&[pattern.to_string()]),
+                    Err(GrindError::InvalidLookalike(invalid)) if invalid == pattern
+                ),
+                "{pattern:?}"
+            );
+        }
+    }
+}
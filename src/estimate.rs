@@ -0,0 +1,142 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::base58::BASE58_ALPHABET;
+
+/// Default multiple of the expected attempts after which a grind is
+/// considered suspiciously long
+pub const DEFAULT_WATCHDOG_MULTIPLE: f64 = 10.0;
+
+/// Expected number of attempts to find an address matching `prefix` and
+/// `suffix` (both already lowercased if `case_insensitive`).
+///
+/// Trailing characters are close enough to uniform, but the leading one
+/// is not: 2^256 is only ~17 * 58^43, so most addresses start with one
+/// of the first ~17 characters of the alphabet. This is still an estimate
+/// since it treats everything after the leading character as uniform.
+pub fn expected_attempts(prefix: &str, suffix: &str, case_insensitive: bool) -> f64 {
+    1.0 / (prefix_probability(prefix, case_insensitive)
+        * suffix_probability(suffix, case_insensitive))
+}
+
+/// Probability that a random address starts with `prefix`
+pub fn prefix_probability(prefix: &str, case_insensitive: bool) -> f64 {
+    // Leading 1s are leading zero bytes rather than base58 digits
+    let ones = prefix.chars().take_while(|&c| c == '1').count().min(32);
+    let zero_bytes = (1.0 / 256.0_f64).powi(ones as i32);
+
+    let mut chars = prefix[ones..].chars();
+    let Some(first) = chars.next() else {
+        return zero_bytes;
+    };
+    zero_bytes
+        * leading_char_probability(first, case_insensitive, 32 - ones as i32)
+        * chars
+            .map(|c| char_probability(c, case_insensitive))
+            .product::<f64>()
+}
+
+/// Probability that a random address ends with `suffix`
+pub fn suffix_probability(suffix: &str, case_insensitive: bool) -> f64 {
+    suffix
+        .chars()
+        .map(|c| char_probability(c, case_insensitive))
+        .product()
+}
+
+/// Probability that a random address has the chars of `pattern` at
+/// their positions, `?` matching any char there
+pub fn pattern_probability(pattern: &str, case_insensitive: bool) -> f64 {
+    // Only the chars before the first wildcard are leading ones
+    let (leading, rest) = pattern.split_at(pattern.find('?').unwrap_or(pattern.len()));
+    prefix_probability(leading, case_insensitive)
+        * rest
+            .chars()
+            .filter(|&c| c != '?')
+            .map(|c| char_probability(c, case_insensitive))
+            .product::<f64>()
+}
+
+/// Rough probability that a random (~44 char) address contains `needle`
+pub fn contains_probability(needle: &str, case_insensitive: bool) -> f64 {
+    let positions = 44_usize.saturating_sub(needle.len()) + 1;
+    (positions as f64 * suffix_probability(needle, case_insensitive)).min(1.0)
+}
+
+/// Probability that a random `bytes` byte value with no leading zero byte
+/// starts with `c` when base58 encoded
+fn leading_char_probability(c: char, case_insensitive: bool, bytes: i32) -> f64 {
+    let min = 2.0_f64.powi(8 * (bytes - 1));
+    let max = 2.0_f64.powi(8 * bytes);
+
+    // Sum over every encoded length the values with a matching first digit
+    let mut probability = 0.0;
+    // A leading digit is never 0, i.e. '1'
+    for digit in matching_digits(c, case_insensitive).filter(|&d| d > 0.0) {
+        let mut scale = 1.0;
+        while digit * scale < max {
+            let lo = (digit * scale).max(min);
+            let hi = ((digit + 1.0) * scale).min(max);
+            if lo < hi {
+                probability += (hi - lo) / (max - min);
+            }
+            scale *= 58.0;
+        }
+    }
+    probability
+}
+
+/// Probability that a uniformly random base58 character matches `c`
+fn char_probability(c: char, case_insensitive: bool) -> f64 {
+    matching_digits(c, case_insensitive).count().max(1) as f64 / 58.0
+}
+
+/// Base58 digits whose characters compare equal to `c`
+fn matching_digits(c: char, case_insensitive: bool) -> impl Iterator<Item = f64> {
+    // Mirrors maybe_bs58_aware_lowercase: L is never folded
+    let fold = move |c: char| {
+        if case_insensitive && c != 'L' {
+            c.to_ascii_lowercase()
+        } else {
+            c
+        }
+    };
+    BASE58_ALPHABET
+        .chars()
+        .enumerate()
+        .filter(move |&(_, a)| fold(a) == fold(c))
+        .map(|(digit, _)| digit as f64)
+}
+
+/// Warns (once) when a grind runs far past its expected attempts, which
+/// usually means a bad target or a throttled machine.
+pub struct Watchdog {
+    /// Per-worker attempt count at which to warn
+    threshold: u64,
+    warned: AtomicBool,
+}
+
+impl Watchdog {
+    /// A non-positive `multiple` disables the watchdog
+    pub fn new(expected: f64, multiple: f64, workers: u32) -> Watchdog {
+        let threshold = if multiple > 0.0 {
+            (expected * multiple / workers.max(1) as f64).max(1.0) as u64
+        } else {
+            u64::MAX
+        };
+
+        Watchdog {
+            threshold,
+            warned: AtomicBool::new(false),
+        }
+    }
+
+    /// Called when a worker's attempt count moves from `before` to
+    /// `after`. Returns true for exactly one worker, the first time the
+    /// threshold is crossed.
+    pub fn crossed(&self, before: u64, after: u64) -> bool {
+        // Only touch the atomic on the crossing to keep the hot path cheap
+        before < self.threshold
+            && self.threshold <= after
+            && !self.warned.swap(true, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,666 @@
+use std::{
+    any::Any,
+    fmt,
+    ops::ControlFlow,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::Stream;
+use sha2::{Digest, Sha256};
+use solana_pubkey::{Pubkey, PubkeyError, MAX_SEED_LEN};
+
+use crate::{available_cpus, matcher::ByteSum, seed, GrindArgs};
+
+/// Minimum time between progress events in [`grind_stream`]
+const PROGRESS_EVENT_PERIOD: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrindError {
+    /// A prefix, suffix, contains or pattern target has a character that
+    /// isn't base58
+    InvalidTarget { which: &'static str, c: char },
+    /// A regex rule doesn't compile
+    InvalidRegex(String),
+    /// More leading zero bytes than a pubkey has
+    InvalidLeadingZeros(usize),
+    /// A last4 target that isn't exactly 4 base58 chars
+    InvalidLast4(String),
+    /// A sort bound that isn't a pubkey
+    InvalidReference(String),
+    /// An owner `Pubkey::create_with_seed` refuses
+    InvalidOwner(Pubkey),
+    /// A byte sum with a zero modulus, or a remainder that isn't less
+    /// than it or is more than 32 bytes can add up to
+    InvalidByteSum { modulus: u32, remainder: u32 },
+    /// A positional pattern no address can match
+    InvalidPattern(String),
+    /// An empty or non-alphanumeric look-alike pattern
+    InvalidLookalike(String),
+    /// The seed prefix/suffix is unusable
+    InvalidSeed(String),
+    /// A step of --base-chain that `create_with_seed` refuses
+    InvalidBaseChain(String),
+    /// The OS couldn't provide entropy to seed the search rng
+    Rng(String),
+    /// The progress callback asked to stop before a match was found
+    Cancelled,
+    /// [`GrindArgs::max_secs`] ran out before the callbacks stopped it
+    TimedOut(Duration),
+    /// The thread running the grind died
+    Worker(String),
+    /// A grind thread panicked, with its panic message
+    WorkerPanic(String),
+}
+
+impl fmt::Display for GrindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GrindError::InvalidTarget { which, c } => {
+                write!(f, "{which} contains invalid bs58: {c}")
+            }
+            GrindError::InvalidRegex(e) => write!(f, "invalid regex: {e}"),
+            GrindError::InvalidLeadingZeros(n) => {
+                write!(f, "leading zeros must be at most 32, got {n}")
+            }
+            GrindError::InvalidLast4(last4) => {
+                write!(f, "last4 must be exactly 4 base58 chars, got {last4:?}")
+            }
+            GrindError::InvalidReference(reference) => {
+                write!(f, "sort bound is not a pubkey: {reference}")
+            }
+            GrindError::InvalidOwner(owner) => write!(
+                f,
+                "owner {owner} ends with the program derived address marker, so no seeded address can have it"
+            ),
+            GrindError::InvalidByteSum { modulus, remainder } => write!(
+                f,
+                "byte sum modulus must be positive and remainder less than it and at most {}, got \
+                 {remainder} mod {modulus}",
+                ByteSum::MAX_SUM
+            ),
+            GrindError::InvalidPattern(e) => write!(f, "invalid pattern: {e}"),
+            GrindError::InvalidLookalike(pattern) => {
+                write!(f, "look-alike patterns must be ascii letters and digits, got {pattern:?}")
+            }
+            GrindError::InvalidSeed(e) => write!(f, "{e}"),
+            GrindError::InvalidBaseChain(e) => write!(f, "invalid base chain {e}"),
+            GrindError::Rng(e) => write!(f, "no randomness available from the os: {e}"),
+            GrindError::Cancelled => write!(f, "grind was cancelled"),
+            GrindError::TimedOut(budget) => write!(f, "grind timed out after {budget:?}"),
+            GrindError::Worker(e) => write!(f, "grind worker failed: {e}"),
+            GrindError::WorkerPanic(e) => write!(f, "grind worker panicked: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for GrindError {}
+
+/// What owners of seeded addresses may not end with, so they can't be
+/// mistaken for program derived addresses
+const PDA_MARKER: &[u8] = b"ProgramDerivedAddress";
+
+/// Fails if `Pubkey::create_with_seed` refuses `owner`, which would make
+/// every address ground for it unusable
+pub fn check_owner(owner: &Pubkey) -> Result<(), GrindError> {
+    if owner.as_ref().ends_with(PDA_MARKER) {
+        return Err(GrindError::InvalidOwner(*owner));
+    }
+    Ok(())
+}
+
+/// The address `Pubkey::create_with_seed` gives for these, i.e.
+/// sha256(base || seed || owner), failing like it does for seeds over
+/// `MAX_SEED_LEN` bytes and owners ending in the PDA marker.
+///
+/// `raw` skips those checks and just hashes. **A raw address that fails
+/// them is non-canonical: Solana will never create it, so nothing sent to
+/// it on-chain can be recovered.** It's only for tests and reproducing
+/// historical derivations.
+pub fn derive_address(
+    base: &Pubkey,
+    seed: &[u8],
+    owner: &Pubkey,
+    raw: bool,
+) -> Result<Pubkey, PubkeyError> {
+    if !raw {
+        if seed.len() > MAX_SEED_LEN {
+            return Err(PubkeyError::MaxSeedLengthExceeded);
+        }
+        if check_owner(owner).is_err() {
+            return Err(PubkeyError::IllegalOwner);
+        }
+    }
+    let hash: [u8; 32] = Sha256::new()
+        .chain_update(base)
+        .chain_update(seed)
+        .chain_update(owner)
+        .finalize()
+        .into();
+    Ok(Pubkey::new_from_array(hash))
+}
+
+/// One `create_with_seed` step of a base chain: the next base is the
+/// address `seed` and `owner` derive from the previous one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainStep {
+    pub seed: String,
+    pub owner: Pubkey,
+}
+
+/// The base reached by deriving each of `steps` in turn, starting from
+/// `base`. Every step must be one Solana accepts, see [`derive_address`].
+pub fn derive_chain(base: &Pubkey, steps: &[ChainStep]) -> Result<Pubkey, GrindError> {
+    steps.iter().enumerate().try_fold(*base, |base, (i, step)| {
+        derive_address(&base, step.seed.as_bytes(), &step.owner, false).map_err(|e| {
+            GrindError::InvalidBaseChain(format!(
+                "step {} ({}:{}): {e}",
+                i + 1,
+                step.seed,
+                step.owner
+            ))
+        })
+    })
+}
+
+/// Endless random (seed, address) pairs under `base` and `owner`, for
+/// search loops that pick addresses their own way, e.g.
+/// `candidates(base, owner)?.find(|(_, address)| matcher.is_match(&address.to_bytes()))`.
+///
+/// Seeds are 16 alphanumerics, as [`grind_blocking`] uses by default,
+/// which skips building a seed string for every attempt and so is faster.
+/// Fails up front with [`GrindError::Rng`] if the OS has no entropy.
+pub fn candidates(
+    base: Pubkey,
+    owner: Pubkey,
+) -> Result<impl Iterator<Item = (String, Pubkey)>, GrindError> {
+    let base_sha = Sha256::new().chain_update(base);
+    let mut rng = seed::search_rng().map_err(|e| GrindError::Rng(e.to_string()))?;
+    Ok(std::iter::repeat_with(move || {
+        let seed = seed::new_seed(&mut rng, None);
+        let hash: [u8; 32] = base_sha
+            .clone()
+            .chain_update(seed)
+            .chain_update(owner)
+            .finalize()
+            .into();
+        (seed::Affixes::default().seed(&seed), Pubkey::new_from_array(hash))
+    }))
+}
+
+/// A matching address, the base and seed that derive it and what it took
+#[derive(Debug, Clone)]
+pub struct Found {
+    /// One of [`GrindArgs::bases`], or the end of the base chain rather
+    /// than --base
+    pub base: Pubkey,
+    pub seed: String,
+    pub address: Pubkey,
+    pub attempts: u64,
+    pub elapsed: Duration,
+}
+
+/// The closest an address has come to matching so far, see
+/// [`crate::matcher::Matcher::depth`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Best {
+    pub depth: usize,
+    pub target_depth: usize,
+    pub address: Pubkey,
+}
+
+/// How a grind is going, as passed to the [`grind_blocking`] callback
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub attempts: u64,
+    pub elapsed: Duration,
+    /// Only tracked if [`GrindArgs::track_best`] is set
+    pub best: Option<Best>,
+}
+
+#[derive(Debug, Clone)]
+pub enum GrindEvent {
+    Progress {
+        attempts: u64,
+        rate: f64,
+        best: Option<Best>,
+    },
+    Found { seed: String, address: Pubkey },
+    Error(GrindError),
+}
+
+/// Grinds on the current thread until an address matching `args` (see
+/// [`GrindArgs::matcher`]) is found.
+///
+/// Every [`GrindArgs::check_interval`] attempts, `progress` is called
+/// with how it's going so far; returning `ControlFlow::Break` stops the
+/// search with [`GrindError::Cancelled`].
+pub fn grind_blocking(
+    args: &GrindArgs,
+    progress: impl FnMut(&Progress) -> ControlFlow<()>,
+) -> Result<Found, GrindError> {
+    grind_first(args, 0, 1, progress)
+}
+
+/// [`grind_thread`] until its first match
+fn grind_first(
+    args: &GrindArgs,
+    thread: u64,
+    threads: u64,
+    progress: impl FnMut(&Progress) -> ControlFlow<()>,
+) -> Result<Found, GrindError> {
+    let mut first = None;
+    grind_thread(
+        args,
+        thread,
+        threads,
+        |found| {
+            first = Some(found);
+            ControlFlow::Break(())
+        },
+        progress,
+    )?;
+    first.ok_or(GrindError::Cancelled)
+}
+
+/// The search loop behind every cpu grind, as thread `thread` of
+/// `threads`, which only matters to the seeds of [`GrindArgs::worker`].
+///
+/// Hands each match to `found`, whose attempts and time taken count from
+/// the previous match, and calls `progress` as [`grind_blocking`] does
+/// (its attempts count from the start), until either returns
+/// `ControlFlow::Break`. Fails with [`GrindError::TimedOut`] once
+/// [`GrindArgs::max_secs`] is up, checked as often as `progress` is
+/// called.
+pub fn grind_thread(
+    args: &GrindArgs,
+    thread: u64,
+    threads: u64,
+    mut found: impl FnMut(Found) -> ControlFlow<()>,
+    mut progress: impl FnMut(&Progress) -> ControlFlow<()>,
+) -> Result<(), GrindError> {
+    check_owner(&args.owner)?;
+    let matcher = args.matcher()?;
+    let affixes = args.seed_affixes()?;
+    args.check_seed_space(&matcher, 1)?;
+    let check_interval = args.check_interval.max(1);
+    let base = args.effective_base()?;
+    let mut seeds = args.seed_source(thread, threads)?;
+    // Negative or NaN budgets are up at once, and infinite ones never
+    let budget = args
+        .max_secs
+        .map(|secs| Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX));
+
+    // The fixed parts of the seed sit on either side of the random part
+    let base_shas: Vec<_> = std::iter::once(&base)
+        .chain(&args.extra_bases)
+        .map(|base| (base, Sha256::new().chain_update(base).chain_update(affixes.prefix)))
+        .collect();
+    let timer = Instant::now();
+    let mut attempts = 0_u64;
+    // As of the previous match
+    let mut matched = (0_u64, Duration::ZERO);
+    let target_depth = matcher.target_depth();
+    let mut best: Option<Best> = None;
+    loop {
+        let seed = seeds.next_seed(args.charset.as_ref());
+        // Every base gets the same seed, so each hash is a fresh candidate
+        for (base, base_sha) in &base_shas {
+            let pubkey_bytes: [u8; 32] = base_sha
+                .clone()
+                .chain_update(seed)
+                .chain_update(affixes.suffix)
+                .chain_update(args.owner)
+                .finalize()
+                .into();
+
+            attempts += 1;
+
+            let address = Pubkey::new_from_array(pubkey_bytes);
+            if matcher.is_match(&pubkey_bytes) && args.curve_ok(&address) {
+                let seed = affixes.seed(&seed);
+                debug_assert_eq!(Pubkey::create_with_seed(base, &seed, &args.owner), Ok(address));
+                let elapsed = timer.elapsed();
+                let (matched_attempts, matched_elapsed) =
+                    std::mem::replace(&mut matched, (attempts, elapsed));
+                let flow = found(Found {
+                    base: **base,
+                    seed,
+                    address,
+                    attempts: attempts - matched_attempts,
+                    elapsed: elapsed - matched_elapsed,
+                });
+                if flow.is_break() {
+                    return Ok(());
+                }
+            }
+
+            if args.track_best {
+                let depth = matcher.depth(&pubkey_bytes);
+                if best.is_none_or(|best| depth > best.depth) {
+                    best = Some(Best {
+                        depth,
+                        target_depth,
+                        address,
+                    });
+                }
+            }
+
+            if attempts.is_multiple_of(check_interval) {
+                let elapsed = timer.elapsed();
+                if progress(&Progress { attempts, elapsed, best }).is_break() {
+                    return Ok(());
+                }
+                if let Some(budget) = budget.filter(|&budget| elapsed >= budget) {
+                    return Err(GrindError::TimedOut(budget));
+                }
+            }
+        }
+    }
+}
+
+/// Runs `grind`, turning a panic into [`GrindError::WorkerPanic`]
+fn catch_panic(grind: impl FnOnce() -> Result<Found, GrindError>) -> Result<Found, GrindError> {
+    panic::catch_unwind(AssertUnwindSafe(grind))
+        .unwrap_or_else(|payload| Err(GrindError::WorkerPanic(panic_message(payload))))
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+/// Like [`grind_blocking`], split across [`GrindArgs::num_cpus`] threads
+/// (at least one, at most [`available_cpus`]) until any of them finds a
+/// match.
+///
+/// `progress` sees the attempts of every thread, though it's only called
+/// from one of them, so about every `check_interval` of its attempts.
+///
+/// A panic in any thread (including in `progress`) stops the others and
+/// is returned as [`GrindError::WorkerPanic`].
+pub fn grind_parallel(
+    args: &GrindArgs,
+    mut progress: impl FnMut(&Progress) -> ControlFlow<()>,
+) -> Result<Found, GrindError> {
+    let threads = args.num_cpus.clamp(1, available_cpus());
+    if threads == 1 {
+        return catch_panic(|| grind_blocking(args, progress));
+    }
+
+    let stop = AtomicBool::new(false);
+    let total = AtomicU64::new(0);
+    let best = Mutex::new(None::<Best>);
+    let grind = |thread: u32, report: &mut dyn FnMut(&Progress) -> ControlFlow<()>| {
+        let mut reported = 0;
+        let result = catch_panic(|| grind_first(args, thread.into(), threads.into(), |local| {
+            let attempts = total.fetch_add(local.attempts - reported, Ordering::Relaxed)
+                + (local.attempts - reported);
+            reported = local.attempts;
+            let best = {
+                // A panicking thread never leaves the best half-updated
+                let mut best = best.lock().unwrap_or_else(PoisonError::into_inner);
+                if local.best.is_some_and(|local| best.is_none_or(|best| local.depth > best.depth)) {
+                    *best = local.best;
+                }
+                *best
+            };
+            let overall = Progress {
+                attempts,
+                elapsed: local.elapsed,
+                best,
+            };
+            if report(&overall).is_break() {
+                stop.store(true, Ordering::Relaxed);
+            }
+            if stop.load(Ordering::Relaxed) {
+                return ControlFlow::Break(());
+            }
+            ControlFlow::Continue(())
+        }));
+        match &result {
+            Ok(found) => {
+                total.fetch_add(found.attempts - reported, Ordering::Relaxed);
+                stop.store(true, Ordering::Relaxed);
+            }
+            Err(GrindError::WorkerPanic(_) | GrindError::TimedOut(_)) => {
+                stop.store(true, Ordering::Relaxed)
+            }
+            Err(_) => {}
+        }
+        result
+    };
+
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let grind = &grind;
+        let others: Vec<_> = (1..threads)
+            .map(|thread| scope.spawn(move || grind(thread, &mut |_| ControlFlow::Continue(()))))
+            .collect();
+        let first = grind(0, &mut progress);
+        std::iter::once(Ok(first))
+            .chain(others.into_iter().map(|other| other.join()))
+            .collect()
+    });
+
+    let mut error = None;
+    for result in results {
+        let e = match result {
+            Ok(Ok(found)) => {
+                return Ok(Found {
+                    attempts: total.into_inner(),
+                    ..found
+                })
+            }
+            Ok(Err(e)) => e,
+            Err(payload) => GrindError::WorkerPanic(panic_message(payload)),
+        };
+        // Whatever stopped the grind, rather than the cancellations it
+        // caused in the other threads
+        if matches!(error, None | Some(GrindError::Cancelled)) {
+            error = Some(e);
+        }
+    }
+    Err(error.unwrap())
+}
+
+/// Grinds for `args` on tokio's blocking pool (see [`grind_parallel`]),
+/// yielding progress events followed by exactly one `Found` or `Error`.
+/// Dropping the stream stops the grind. Must be called from within a
+/// tokio runtime.
+///
+/// Progress is latest-wins: a consumer that falls behind only sees the
+/// most recent progress, so a slow client never holds up the grinder or
+/// piles up events, while the final event is always delivered. `watch`
+/// sees all of it as [`grind_parallel`]'s callback does, on the grind's
+/// thread and whether or not the stream is read, and is dropped once the
+/// grind is over.
+pub fn grind_stream(
+    args: GrindArgs,
+    mut watch: impl FnMut(&Progress) + Send + 'static,
+) -> impl Stream<Item = GrindEvent> {
+    let (progress_tx, progress_rx) = tokio::sync::watch::channel((0, 0.0, None));
+
+    let worker = tokio::task::spawn_blocking(move || {
+        let mut last_event = Duration::ZERO;
+        grind_parallel(&args, |progress| {
+            if progress_tx.is_closed() {
+                return ControlFlow::Break(());
+            }
+            watch(progress);
+            if progress.elapsed - last_event >= PROGRESS_EVENT_PERIOD {
+                last_event = progress.elapsed;
+                let rate = progress.attempts as f64 / progress.elapsed.as_secs_f64();
+                progress_tx.send_replace((progress.attempts, rate, progress.best));
+            }
+            ControlFlow::Continue(())
+        })
+    });
+
+    // The worker's own result is the final event, so it can't be lost to
+    // a full channel, and a panicking worker still ends the stream
+    futures::stream::unfold(Some((progress_rx, worker)), |state| async move {
+        let (mut progress, mut worker) = state?;
+        tokio::select! {
+            biased;
+            joined = &mut worker => {
+                let event = match joined {
+                    Ok(Ok(found)) => GrindEvent::Found {
+                        seed: found.seed,
+                        address: found.address,
+                    },
+                    Ok(Err(e)) => GrindEvent::Error(e),
+                    Err(e) => GrindEvent::Error(GrindError::Worker(e.to_string())),
+                };
+                Some((event, None))
+            }
+            Ok(()) = progress.changed() => {
+                let (attempts, rate, best) = *progress.borrow_and_update();
+                let event = GrindEvent::Progress { attempts, rate, best };
+                Some((event, Some((progress, worker))))
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{distributions::Alphanumeric, Rng};
+
+    use super::*;
+    use crate::{
+        matcher::{MatcherSpec, RuleKind, RuleSpec},
+        owners,
+    };
+
+    #[test]
+    fn derive_address_agrees_with_create_with_seed() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let base = Pubkey::new_from_array(rng.gen());
+            let owner = Pubkey::new_from_array(rng.gen());
+            // Sometimes over MAX_SEED_LEN bytes, and sometimes with chars
+            // of more than one byte
+            let len = rng.gen_range(0..=MAX_SEED_LEN + 2);
+            let seed: String = (0..len)
+                .map(|_| {
+                    if rng.gen_ratio(1, 8) {
+                        'é'
+                    } else {
+                        rng.sample(Alphanumeric).into()
+                    }
+                })
+                .collect();
+            assert_eq!(
+                derive_address(&base, seed.as_bytes(), &owner, false),
+                Pubkey::create_with_seed(&base, &seed, &owner),
+                "{base} {seed:?} {owner}"
+            );
+        }
+    }
+
+    /// Args for grinding addresses ending in `suffix` under the system
+    /// program's base, on one thread
+    fn suffix_args(suffix: &str) -> GrindArgs {
+        GrindArgs {
+            suffix: Some(suffix.to_string()),
+            num_cpus: 1,
+            ..GrindArgs::new(owners::SYSTEM_PROGRAM, owners::TOKEN_PROGRAM)
+        }
+    }
+
+    #[test]
+    fn off_curve_matches_are_off_curve() {
+        let args = GrindArgs {
+            require_off_curve: true,
+            ..suffix_args("a")
+        };
+        // About half of all addresses are on the curve
+        for _ in 0..8 {
+            let found = grind_blocking(&args, |_| ControlFlow::Continue(())).unwrap();
+            assert!(!found.address.is_on_curve(), "{}", found.address);
+            assert!(found.address.to_string().ends_with('a'), "{}", found.address);
+            let derived = derive_address(&found.base, found.seed.as_bytes(), &args.owner, false);
+            assert_eq!(derived, Ok(found.address));
+        }
+    }
+
+    /// Args that never match, since a regex isn't estimated and nothing
+    /// encodes to an empty string, so only the callbacks stop the grind
+    fn never_args() -> GrindArgs {
+        let rule = RuleSpec {
+            kind: RuleKind::Regex("^$".to_string()),
+            case_insensitive: false,
+        };
+        GrindArgs {
+            matcher: Some(MatcherSpec {
+                rules: vec![rule],
+                ..MatcherSpec::default()
+            }),
+            check_interval: 100,
+            ..GrindArgs::new(owners::SYSTEM_PROGRAM, owners::TOKEN_PROGRAM)
+        }
+    }
+
+    #[test]
+    fn panics_stop_the_grind() {
+        for num_cpus in [1, 2] {
+            let args = GrindArgs {
+                num_cpus,
+                ..never_args()
+            };
+            let (done, result) = std::sync::mpsc::channel();
+            std::thread::spawn(move || {
+                let mut calls = 0;
+                let grind = grind_parallel(&args, |_| {
+                    calls += 1;
+                    if calls == 3 {
+                        panic!("progress gave up");
+                    }
+                    ControlFlow::Continue(())
+                });
+                done.send(grind).unwrap();
+            });
+            let result = result.recv_timeout(Duration::from_secs(30)).expect("grind hung");
+            assert_eq!(
+                result.unwrap_err(),
+                GrindError::WorkerPanic("progress gave up".to_string()),
+                "{num_cpus} threads"
+            );
+        }
+    }
+
+    #[test]
+    fn pda_marker_owners_are_refused() {
+        let mut bytes = [7; 32];
+        bytes[32 - PDA_MARKER.len()..].copy_from_slice(PDA_MARKER);
+        let owner = Pubkey::new_from_array(bytes);
+        assert_eq!(check_owner(&owner), Err(GrindError::InvalidOwner(owner)));
+        // Like create_with_seed, unless asked for the raw hash
+        let base = owners::SYSTEM_PROGRAM;
+        assert_eq!(derive_address(&base, b"seed", &owner, false), Err(PubkeyError::IllegalOwner));
+        assert_eq!(
+            Pubkey::create_with_seed(&base, "seed", &owner),
+            Err(PubkeyError::IllegalOwner)
+        );
+        assert!(derive_address(&base, b"seed", &owner, true).is_ok());
+        // So does a grind for it, rather than finding unusable addresses
+        let args = suffix_args("a");
+        assert!(matches!(
+            grind_blocking(&GrindArgs { owner, ..args }, |_| ControlFlow::Continue(())),
+            Err(GrindError::InvalidOwner(_))
+        ));
+
+        for owner in [owners::SYSTEM_PROGRAM, owners::TOKEN_PROGRAM] {
+            assert_eq!(check_owner(&owner), Ok(()));
+        }
+    }
+}
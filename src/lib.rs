@@ -0,0 +1,497 @@
+use std::{path::PathBuf, str::FromStr};
+
+use clap::Parser;
+use solana_pubkey::Pubkey;
+
+pub mod attestation;
+pub mod base58;
+pub mod confusable;
+pub mod estimate;
+pub mod grind;
+pub mod matcher;
+pub mod owners;
+pub mod seed;
+
+use base58::is_base58_char;
+use estimate::DEFAULT_WATCHDOG_MULTIPLE;
+use grind::{ChainStep, GrindError};
+use matcher::{ByteSum, Matcher, MatcherSpec};
+use seed::{Affixes, Charset, Partition, SeedSource};
+
+/// By default the byte-level prefix check covers as much of the prefix as
+/// it can (i.e. all of it, short of many case-insensitive letters)
+pub const DEFAULT_PREFIX_FILTER_CHARS: usize = 44;
+
+/// Attempts between checks of the stop conditions. Checking the clock and
+/// shared flags every attempt costs ~5-10% throughput, while anything
+/// over a few hundred is within noise; at ~2.5M attempts/s per thread
+/// this still stops within a couple of milliseconds.
+pub const DEFAULT_CHECK_INTERVAL: u64 = 4096;
+
+/// How many times as many matching seeds as requested matches a charset
+/// must be expected to leave, see [`GrindArgs::check_seed_space`]. Past
+/// that, repeated seeds are rare enough that skipping them costs nothing.
+pub const MIN_SPARE_SEEDS: f64 = 10.0;
+
+#[derive(Debug, Parser)]
+pub struct GrindArgs {
+    /// The pubkey that will be the signer for the CreateAccountWithSeed instruction
+    #[clap(long, value_parser = parse_pubkey)]
+    pub base: Pubkey,
+
+    /// Derive the base to grind under from --base by create_with_seed steps, in order: each SEED:OWNER turns the base so far into the address they derive from it, e.g. --base-chain vault:system --base-chain pool:token. Repeatable; the owner is a pubkey or a name as for --owner. Creating the ground account then needs the last derived base's signature
+    #[clap(long = "base-chain", value_name = "SEED:OWNER", value_parser = parse_chain_step)]
+    pub base_chain: Vec<ChainStep>,
+
+    /// More bases to try every seed under too, so one hash of seed work covers each. Repeatable. cpu only
+    #[clap(long = "also-base", value_name = "BASE", value_parser = parse_pubkey)]
+    pub extra_bases: Vec<Pubkey>,
+
+    /// The account owner, e.g. BPFLoaderUpgradeab1e11111111111111111111111, or one of system, token, token-2022, stake or associated-token
+    #[clap(long, value_parser = parse_owner)]
+    pub owner: Pubkey,
+
+    /// The target prefix for the pubkey
+    #[clap(long)]
+    pub prefix: Option<String>,
+
+    #[clap(long)]
+    pub suffix: Option<String>,
+
+    /// The exact last 4 chars of the pubkey, as wallets often show them. A suffix that must be 4 base58 chars
+    #[clap(long, conflicts_with = "suffix")]
+    pub last4: Option<String>,
+
+    /// Whether user cares about the case of the pubkey
+    #[clap(long, default_value_t = false)]
+    pub case_insensitive: bool,
+
+    /// Require these chars at the same positions from the start, where ? is any char, e.g. So?Lab. ANDed with the other targets
+    #[clap(long)]
+    pub pattern: Option<String>,
+
+    /// Reject addresses that look like they contain this, e.g. a brand to keep look-alikes of off the pool. Compared case-insensitively with digits folded into the letters they resemble (PayPa1 looks like PayPal) and rn/vv into m/w. Repeatable
+    #[clap(long = "avoid-lookalike", value_name = "PATTERN")]
+    pub avoid_lookalikes: Vec<String>,
+
+    /// Require at least this many leading zero bytes, which show as leading 1s. Checked before encoding
+    #[clap(long)]
+    pub leading_zeros: Option<usize>,
+
+    /// Require the pubkey's bytes to sum to REMAINDER mod MODULUS, e.g. 16:3 for one of 16 shards. Checked before encoding, and ANDed with the other targets
+    #[clap(long, value_name = "MODULUS:REMAINDER", value_parser = parse_byte_sum)]
+    pub byte_sum: Option<ByteSum>,
+
+    /// Require sorting before this pubkey, comparing bytes. Checked before encoding
+    #[clap(long, value_parser = parse_pubkey)]
+    pub sorts_before: Option<Pubkey>,
+
+    /// Require sorting after this pubkey, comparing bytes. Checked before encoding
+    #[clap(long, value_parser = parse_pubkey)]
+    pub sorts_after: Option<Pubkey>,
+
+    /// Optional log file
+    #[clap(long)]
+    pub logfile: Option<String>,
+
+    /// Log every match instead of drawing a progress bar, which is only drawn on a terminal anyway
+    #[cfg(feature = "progress")]
+    #[clap(long, default_value_t = false)]
+    pub quiet: bool,
+
+    /// Number of gpus to use for mining
+    #[clap(long, default_value_t = 1)]
+    #[cfg(feature = "gpu")]
+    pub num_gpus: u32,
+
+    /// Number of cpu threads to use for mining
+    #[clap(long, default_value_t = 0)]
+    pub num_cpus: u32,
+
+    /// Warn when a grind exceeds this multiple of its expected attempts (0 disables)
+    #[clap(long, default_value_t = DEFAULT_WATCHDOG_MULTIPLE)]
+    pub watchdog_multiple: f64,
+
+    /// Optional file to write the resulting address to
+    #[clap(long)]
+    pub address_out: Option<PathBuf>,
+
+    /// Optional file to write the resulting seed to
+    #[clap(long)]
+    pub seed_out: Option<PathBuf>,
+
+    /// Leading prefix chars to check on the raw pubkey bytes before base58 encoding (0 disables). cpu only
+    #[clap(long, default_value_t = DEFAULT_PREFIX_FILTER_CHARS)]
+    pub prefix_filter_chars: usize,
+
+    /// Attempts between checks for ctrl-c, the time budget and cancellation. Higher is cheaper but slower to stop. cpu only
+    #[clap(long, default_value_t = DEFAULT_CHECK_INTERVAL, value_parser = clap::value_parser!(u64).range(1..))]
+    pub check_interval: u64,
+
+    /// Characters to build seeds from, either plain (abc123) or weighted (0:4,1:4,a:1). cpu only
+    #[clap(long)]
+    pub charset: Option<Charset>,
+
+    /// Fixed text every seed starts with, e.g. a batch tag. cpu only
+    #[clap(long)]
+    pub seed_prefix: Option<String>,
+
+    /// Fixed text every seed ends with. cpu only
+    #[clap(long)]
+    pub seed_suffix: Option<String>,
+
+    /// Enumerate seeds from counters instead of at random, as worker INDEX of COUNT taking the counters ≡ INDEX mod COUNT, e.g. --worker 2/8. Workers sharing every other flag then never try the same seed. cpu only
+    #[clap(long, value_name = "INDEX/COUNT")]
+    pub worker: Option<Partition>,
+
+    /// The counter --worker starts from, the same for every worker, e.g. to move a rerun past counters already tried
+    #[clap(long, default_value_t = 0, requires = "worker")]
+    pub counter_start: u64,
+
+    /// Only accept addresses off the ed25519 curve, which no private key can sign for. Checked after the target matches
+    #[clap(long, default_value_t = false)]
+    pub require_off_curve: bool,
+
+    /// Keep grinding after a match, collecting every match until a budget runs out or ctrl-c
+    #[clap(long, default_value_t = false)]
+    pub continuous: bool,
+
+    /// File to append matches to as json lines
+    #[clap(long)]
+    pub pool_out: Option<PathBuf>,
+
+    /// Add generated_at (RFC 3339), hostname and version to every --pool-out record
+    #[clap(long, default_value_t = false, requires = "pool_out")]
+    pub pool_metadata: bool,
+
+    /// Stop after this many matches (continuous mode). Every match gets a distinct seed, tracked at roughly 100 bytes per match
+    #[clap(long)]
+    pub max_matches: Option<u64>,
+
+    /// Stop after this many seconds
+    #[clap(long)]
+    pub max_secs: Option<f64>,
+
+    /// Grind continuously for --max-secs, then print a json report of matches per minute and attempts per match for capacity testing
+    #[clap(long, default_value_t = false, requires = "max_secs")]
+    pub benchmark: bool,
+
+    /// Compact target used instead of --prefix/--suffix/--case-insensitive: Sol*Loop (prefix and suffix), Sol* or *Loop, abc (contains), ~ prefix for case-insensitive, \ escapes
+    #[clap(long = "target", value_name = "TARGET", value_parser = matcher::parse_target_spec, conflicts_with_all = ["prefix", "suffix", "last4", "pattern", "case_insensitive", "leading_zeros", "byte_sum", "sorts_before", "sorts_after"])]
+    pub matcher: Option<MatcherSpec>,
+
+    /// Track the closest address so far for progress reports, which costs
+    /// a match depth computation every attempt
+    #[clap(skip)]
+    pub track_best: bool,
+}
+
+impl GrindArgs {
+    /// Args for grinding under `base` and `owner` with the cli defaults
+    /// and no target, to be filled in with struct update syntax
+    pub fn new(base: Pubkey, owner: Pubkey) -> GrindArgs {
+        GrindArgs {
+            base,
+            base_chain: Vec::new(),
+            extra_bases: Vec::new(),
+            owner,
+            prefix: None,
+            suffix: None,
+            last4: None,
+            pattern: None,
+            case_insensitive: false,
+            avoid_lookalikes: Vec::new(),
+            leading_zeros: None,
+            byte_sum: None,
+            sorts_before: None,
+            sorts_after: None,
+            logfile: None,
+            #[cfg(feature = "progress")]
+            quiet: false,
+            #[cfg(feature = "gpu")]
+            num_gpus: 1,
+            num_cpus: 0,
+            watchdog_multiple: DEFAULT_WATCHDOG_MULTIPLE,
+            address_out: None,
+            seed_out: None,
+            prefix_filter_chars: DEFAULT_PREFIX_FILTER_CHARS,
+            check_interval: DEFAULT_CHECK_INTERVAL,
+            charset: None,
+            seed_prefix: None,
+            seed_suffix: None,
+            worker: None,
+            counter_start: 0,
+            require_off_curve: false,
+            continuous: false,
+            pool_out: None,
+            pool_metadata: false,
+            max_matches: None,
+            max_secs: None,
+            benchmark: false,
+            matcher: None,
+            track_best: false,
+        }
+    }
+
+    /// The base seeds are ground under in place of --base: the end of
+    /// [`GrindArgs::base_chain`] if it has any steps, validating each
+    pub fn effective_base(&self) -> Result<Pubkey, GrindError> {
+        grind::derive_chain(&self.base, &self.base_chain)
+    }
+
+    /// Every base to grind under, --base first. Doesn't follow
+    /// [`GrindArgs::base_chain`], see [`GrindArgs::effective_base`]
+    pub fn bases(&self) -> impl Iterator<Item = &Pubkey> {
+        std::iter::once(&self.base).chain(&self.extra_bases)
+    }
+
+    /// The matcher spec these args describe
+    pub fn matcher_spec(&self) -> MatcherSpec {
+        let mut spec = self.matcher.clone().unwrap_or_else(|| {
+            let mut spec = MatcherSpec::from_target(
+                self.prefix.as_deref(),
+                self.last4.as_deref().or(self.suffix.as_deref()),
+                self.case_insensitive,
+            );
+            spec.add_pattern(self.pattern.as_deref(), self.case_insensitive);
+            spec.add_leading_zeros(self.leading_zeros);
+            spec.add_byte_sum(self.byte_sum);
+            spec.add_sort_bounds(self.sorts_before.as_ref(), self.sorts_after.as_ref());
+            spec
+        });
+        spec.avoid_lookalikes.extend(self.avoid_lookalikes.iter().cloned());
+        spec
+    }
+
+    /// The validated matcher these args describe
+    pub fn matcher(&self) -> Result<Matcher, GrindError> {
+        if let Some(last4) = &self.last4 {
+            validated_last4(last4)?;
+        }
+        Matcher::new(&self.matcher_spec(), self.prefix_filter_chars)
+    }
+
+    /// The validated fixed parts of every seed
+    pub fn seed_affixes(&self) -> Result<Affixes<'_>, GrindError> {
+        Affixes::new(
+            self.seed_prefix.as_deref().unwrap_or_default(),
+            self.seed_suffix.as_deref().unwrap_or_default(),
+        )
+        .map_err(GrindError::InvalidSeed)
+    }
+
+    /// Seed parts for thread `thread` of `threads`, per
+    /// [`GrindArgs::worker`]. Fails if random seeds need entropy the OS
+    /// can't give
+    pub fn seed_source(&self, thread: u64, threads: u64) -> Result<SeedSource, GrindError> {
+        self.seed_source_from(thread, threads, &mut rand::rngs::OsRng)
+    }
+
+    /// [`GrindArgs::seed_source`], with `entropy` standing in for the OS
+    pub(crate) fn seed_source_from(
+        &self,
+        thread: u64,
+        threads: u64,
+        entropy: &mut impl rand::RngCore,
+    ) -> Result<SeedSource, GrindError> {
+        SeedSource::from_entropy(self.worker, self.counter_start, thread, threads, entropy)
+            .map_err(|e| GrindError::Rng(e.to_string()))
+    }
+
+    /// Whether an address that matched the target satisfies
+    /// [`GrindArgs::require_off_curve`]. The check costs far more than
+    /// matching, so only call it on matches
+    pub fn curve_ok(&self, address: &Pubkey) -> bool {
+        !self.require_off_curve || !address.is_on_curve()
+    }
+
+    /// Fails if the charset leaves too few seeds to expect
+    /// [`MIN_SPARE_SEEDS`] times `matches` distinct matching seeds under
+    /// [`GrindArgs::bases`], so a grind could run out of seeds that match
+    pub fn check_seed_space(&self, matcher: &Matcher, matches: u64) -> Result<(), GrindError> {
+        // Regexes can't be estimated, so get the benefit of the doubt
+        let Some(expected) = matcher.expected_attempts() else {
+            return Ok(());
+        };
+        let bases = self.bases().count() as f64;
+        let bits = seed::seed_space_bits(self.charset.as_ref()) + bases.log2();
+        let matching_bits = bits - expected.log2();
+        if matching_bits < (matches as f64 * MIN_SPARE_SEEDS).log2() {
+            return Err(GrindError::InvalidSeed(format!(
+                "charset only makes 2^{bits:.1} seeds, so about 2^{matching_bits:.1} match the \
+                 target, too few for {matches} distinct matches; use more seed characters"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Validates that `target` (the prefix or suffix, named by `which`) is
+/// base58 and normalizes its case for matching. None becomes "".
+pub fn validated_target(
+    which: &'static str,
+    target: &Option<String>,
+    case_insensitive: bool,
+) -> Result<String, GrindError> {
+    // Validate target (i.e. does it include 0, O, I, l)
+    //
+    // maybe TODO: technically we could accept I or o if case-insensitivity but I suspect
+    // most users will provide lowercase targets for case-insensitive searches
+    let Some(target) = target else {
+        return Ok(String::new());
+    };
+    if let Some(c) = target.chars().find(|&c| !is_base58_char(c)) {
+        return Err(GrindError::InvalidTarget { which, c });
+    }
+    Ok(maybe_bs58_aware_lowercase(target, case_insensitive))
+}
+
+/// Checks a last4 target is exactly 4 base58 chars
+pub fn validated_last4(last4: &str) -> Result<&str, GrindError> {
+    if last4.chars().count() != 4 || !last4.chars().all(is_base58_char) {
+        return Err(GrindError::InvalidLast4(last4.to_string()));
+    }
+    Ok(last4)
+}
+
+pub fn maybe_bs58_aware_lowercase(target: &str, case_insensitive: bool) -> String {
+    // L is only char that shouldn't be converted to lowercase in case-insensitivity case
+    const LOWERCASE_EXCEPTIONS: &str = "L";
+
+    if case_insensitive {
+        target
+            .chars()
+            .map(|c| {
+                if LOWERCASE_EXCEPTIONS.contains(c) {
+                    c
+                } else {
+                    c.to_ascii_lowercase()
+                }
+            })
+            .collect::<String>()
+    } else {
+        target.to_string()
+    }
+}
+
+/// The address as UIs tend to show it, its first `head` and last `tail`
+/// chars around an ellipsis, e.g. Vnty…Loop. Addresses too short to
+/// shorten come back whole.
+pub fn short_address(address: &str, head: usize, tail: usize) -> String {
+    let len = address.chars().count();
+    // Both come from clients, so they may add up to more than a usize
+    if len <= head.saturating_add(tail) {
+        return address.to_string();
+    }
+    let start: String = address.chars().take(head).collect();
+    let end: String = address.chars().skip(len - tail).collect();
+    format!("{start}…{end}")
+}
+
+/// Cores available to grind on, at least 1
+pub fn available_cpus() -> u32 {
+    std::thread::available_parallelism().map_or(1, |n| n.get() as u32)
+}
+
+pub fn parse_pubkey(input: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(input).map_err(|e| e.to_string())
+}
+
+/// Parses a pubkey or the name of a program in [`owners::NAMED`]
+pub fn parse_owner(input: &str) -> Result<Pubkey, String> {
+    owners::by_name(input).map_or_else(|| parse_pubkey(input), Ok)
+}
+
+/// Parses a SEED:OWNER step of a base chain, leaving its validation to
+/// [`grind::derive_chain`]. Splits at the last `:`, which can't be part
+/// of an owner
+pub fn parse_chain_step(input: &str) -> Result<ChainStep, String> {
+    let (seed, owner) = input
+        .rsplit_once(':')
+        .ok_or("expected SEED:OWNER, e.g. vault:system")?;
+    Ok(ChainStep {
+        seed: seed.to_string(),
+        owner: parse_owner(owner)?,
+    })
+}
+
+/// Parses MODULUS:REMAINDER, leaving its validation to the matcher
+pub fn parse_byte_sum(input: &str) -> Result<ByteSum, String> {
+    let (modulus, remainder) = input
+        .split_once(':')
+        .ok_or("expected MODULUS:REMAINDER, e.g. 16:3")?;
+    let number = |n: &str| n.parse::<u32>().map_err(|e| format!("{n:?}: {e}"));
+    Ok(ByteSum {
+        modulus: number(modulus)?,
+        remainder: number(remainder)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An rng out of entropy, like an OS without a usable getrandom. Only
+    /// the fallible method fails gracefully, so the others panic
+    struct NoEntropy;
+
+    impl rand::RngCore for NoEntropy {
+        fn next_u32(&mut self) -> u32 {
+            panic!("asked for entropy without a way to fail");
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            panic!("asked for entropy without a way to fail");
+        }
+
+        fn fill_bytes(&mut self, _dest: &mut [u8]) {
+            panic!("asked for entropy without a way to fail");
+        }
+
+        fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand::Error> {
+            Err(rand::Error::new("no entropy here"))
+        }
+    }
+
+    #[test]
+    fn missing_entropy_is_an_error() {
+        let args = GrindArgs::new(owners::SYSTEM_PROGRAM, owners::TOKEN_PROGRAM);
+        match args.seed_source_from(0, 1, &mut NoEntropy) {
+            Err(GrindError::Rng(e)) => assert!(e.contains("no entropy here"), "{e}"),
+            other => panic!("expected an rng error, got {other:?}"),
+        }
+        // Counters need no entropy
+        let worker = GrindArgs {
+            worker: Some("0/2".parse().unwrap()),
+            ..args
+        };
+        assert!(worker.seed_source_from(0, 1, &mut NoEntropy).is_ok());
+    }
+
+    #[test]
+    fn last4_is_exactly_four_base58_chars() {
+        assert_eq!(validated_last4("Loop"), Ok("Loop"));
+        assert_eq!(validated_last4("1111"), Ok("1111"));
+        // Too short, too long, or with a char base58 leaves out
+        for last4 in ["", "abc", "abcde", "Lo0p", "LOop", "Ioop", "lo0p", "abc "] {
+            assert_eq!(
+                validated_last4(last4),
+                Err(GrindError::InvalidLast4(last4.to_string())),
+                "{last4:?}"
+            );
+        }
+        // Counted in chars, so a multibyte char doesn't pass as several
+        assert!(validated_last4("abé").is_err());
+        assert!(validated_last4("abcé").is_err());
+    }
+
+    #[test]
+    fn short_addresses_keep_the_ends() {
+        let address = "VntyabcdefLoop";
+        assert_eq!(short_address(address, 4, 4), "Vnty…Loop");
+        assert_eq!(short_address(address, 0, 4), "…Loop");
+        assert_eq!(short_address(address, 7, 7), address);
+        assert_eq!(short_address(address, usize::MAX, 1), address);
+        assert_eq!(short_address(address, 1, usize::MAX), address);
+    }
+}
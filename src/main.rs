@@ -1,8 +1,9 @@
 use clap::Parser;
 use logfather::{Level, Logger};
 use num_format::{Locale, ToFormattedString};
-use rand::{distributions::Alphanumeric, Rng};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
+#[cfg(feature = "gpu")]
 use sha2::{Digest, Sha256};
 use solana_pubkey::Pubkey;
 use solana_rpc_client::rpc_client::RpcClient;
@@ -10,62 +11,54 @@ use solana_sdk::{
     bpf_loader_upgradeable::{self, get_program_data_address, UpgradeableLoaderState},
     instruction::{AccountMeta, Instruction},
     loader_upgradeable_instruction::UpgradeableLoaderInstruction,
-    signature::read_keypair_file,
+    signature::{read_keypair_file, Signature},
     signer::Signer,
     system_instruction, system_program, sysvar,
     transaction::Transaction,
 };
 
 use std::{
-    array,
-    path::PathBuf,
-    str::FromStr,
-    sync::atomic::{AtomicBool, Ordering},
-    time::Instant,
+    cell::Cell,
+    collections::HashSet,
+    fs::File,
+    io::Write,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
 };
 
+use vanity::{
+    estimate::Watchdog,
+    attestation::verify_attestation,
+    grind::{check_owner, derive_address, grind_thread, GrindError, Progress},
+    parse_owner, parse_pubkey, GrindArgs,
+};
+#[cfg(feature = "gpu")]
+use vanity::{maybe_bs58_aware_lowercase, validated_target};
+
+mod cache;
+mod metadata;
+mod metrics;
+#[cfg(feature = "progress")]
+mod progress;
 mod server;
+#[cfg(feature = "webhook")]
+mod webhook;
+
+use metadata::Metadata;
 
+// Only ever parsed once, so its size doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Parser)]
 pub enum Command {
     Grind(GrindArgs),
     Deploy(DeployArgs),
-    Server,
-}
-
-#[derive(Debug, Parser)]
-pub struct GrindArgs {
-    /// The pubkey that will be the signer for the CreateAccountWithSeed instruction
-    #[clap(long, value_parser = parse_pubkey)]
-    pub base: Pubkey,
-
-    /// The account owner, e.g. BPFLoaderUpgradeab1e11111111111111111111111 or TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA
-    #[clap(long, value_parser = parse_pubkey)]
-    pub owner: Pubkey,
-
-    /// The target prefix for the pubkey
-    #[clap(long)]
-    pub prefix: Option<String>,
-
-    #[clap(long)]
-    pub suffix: Option<String>,
-
-    /// Whether user cares about the case of the pubkey
-    #[clap(long, default_value_t = false)]
-    pub case_insensitive: bool,
-
-    /// Optional log file
-    #[clap(long)]
-    pub logfile: Option<String>,
-
-    /// Number of gpus to use for mining
-    #[clap(long, default_value_t = 1)]
-    #[cfg(feature = "gpu")]
-    pub num_gpus: u32,
-
-    /// Number of cpu threads to use for mining
-    #[clap(long, default_value_t = 0)]
-    pub num_cpus: u32,
+    Server(server::ServerArgs),
+    Verify(VerifyArgs),
 }
 
 #[derive(Debug, Parser)]
@@ -78,8 +71,8 @@ pub struct DeployArgs {
     #[clap(long, default_value = "https://api.mainnet-beta.solana.com")]
     pub rpc: String,
 
-    /// The account owner, e.g. BPFLoaderUpgradeab1e11111111111111111111111 or TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA
-    #[clap(long, value_parser = parse_pubkey)]
+    /// The account owner, e.g. BPFLoaderUpgradeab1e11111111111111111111111, or one of system, token, token-2022, stake or associated-token
+    #[clap(long, value_parser = parse_owner)]
     pub owner: Pubkey,
 
     /// Buffer where the program has been written (via solana program write-buffer)
@@ -107,6 +100,45 @@ pub struct DeployArgs {
     pub logfile: Option<String>,
 }
 
+#[derive(Debug, Parser)]
+pub struct VerifyArgs {
+    /// The base pubkey the seed was ground under
+    #[clap(long, value_parser = parse_pubkey)]
+    pub base: Pubkey,
+
+    /// The seed to check
+    #[clap(long)]
+    pub seed: String,
+
+    /// The account owner, a pubkey or a program name as for grind
+    #[clap(long, value_parser = parse_owner)]
+    pub owner: Pubkey,
+
+    /// Address the seed should derive; exits non-zero if it doesn't
+    #[clap(long, value_parser = parse_pubkey)]
+    pub expected: Option<Pubkey>,
+
+    /// Print the result as json
+    #[clap(long, default_value_t = false)]
+    pub json: bool,
+
+    /// UNSAFE: hash even seeds and owners Solana refuses, e.g. to reproduce historical addresses. Such addresses are non-canonical and can never be created on-chain
+    #[clap(long, default_value_t = false)]
+    pub raw_derivation: bool,
+
+    /// Also check a server's attestation signature over the derived address; exits non-zero if it doesn't verify
+    #[clap(long, requires_all = ["server_pubkey", "timestamp"])]
+    pub signature: Option<Signature>,
+
+    /// The server_pubkey the attestation was signed by
+    #[clap(long, value_parser = parse_pubkey, requires = "signature")]
+    pub server_pubkey: Option<Pubkey>,
+
+    /// The attestation's timestamp
+    #[clap(long, requires = "signature")]
+    pub timestamp: Option<u64>,
+}
+
 static EXIT: AtomicBool = AtomicBool::new(false);
 
 fn main() {
@@ -123,15 +155,100 @@ fn main() {
             deploy(args);
         }
 
-        Command::Server => {
+        Command::Verify(args) => {
+            verify(args);
+        }
+
+        Command::Server(args) => {
             // Start the HTTP server
-            tokio::runtime::Runtime::new()
+            let result = tokio::runtime::Runtime::new()
                 .unwrap()
-                .block_on(server::start_server());
+                .block_on(server::start_server(args));
+            if let Err(e) = result {
+                eprintln!("server failed: {e}");
+                std::process::exit(1);
+            }
         }
     }
 }
 
+#[derive(Serialize)]
+struct VerifyResult {
+    /// Missing if create_with_seed refuses the seed or owner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<String>,
+    /// Whether create_with_seed accepts the seed and owner, even if
+    /// --raw-derivation derived the address anyway
+    valid_seed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Whether the attestation verifies, if --signature was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attestation_valid: Option<bool>,
+    matches: bool,
+}
+
+fn verify(args: VerifyArgs) {
+    let seed = args.seed.as_bytes();
+    let canonical = derive_address(&args.base, seed, &args.owner, false);
+    let valid_seed = canonical.is_ok();
+    let derived = match canonical {
+        Err(_) if args.raw_derivation => derive_address(&args.base, seed, &args.owner, true),
+        canonical => canonical,
+    };
+    let attestation_valid = args.signature.map(|signature| {
+        derived.as_ref().is_ok_and(|address| {
+            verify_attestation(
+                address,
+                &args.seed,
+                &args.base,
+                &args.owner,
+                args.timestamp.unwrap_or_default(),
+                &signature,
+                &args.server_pubkey.unwrap_or_default(),
+            )
+        })
+    });
+    let matches = derived
+        .as_ref()
+        .is_ok_and(|address| args.expected.is_none_or(|expected| expected == *address))
+        && attestation_valid != Some(false);
+
+    if args.json {
+        let result = VerifyResult {
+            address: derived.as_ref().ok().map(|address| address.to_string()),
+            expected: args.expected.map(|expected| expected.to_string()),
+            valid_seed,
+            error: derived.as_ref().err().map(|e| e.to_string()),
+            attestation_valid,
+            matches,
+        };
+        println!("{}", serde_json::to_string(&result).unwrap());
+    } else {
+        match &derived {
+            Ok(address) => println!("address = {address}"),
+            Err(e) => println!("{e}; --raw-derivation derives it anyway"),
+        }
+        if !valid_seed && derived.is_ok() {
+            println!("warning: non-canonical address, Solana will never create it");
+        }
+        if let Some(expected) = args.expected {
+            let verdict = if matches { "matches" } else { "does not match" };
+            println!("{verdict} expected {expected}");
+        }
+        if let Some(valid) = attestation_valid {
+            let verdict = if valid { "valid" } else { "invalid" };
+            println!("attestation is {verdict}");
+        }
+    }
+
+    if !matches {
+        std::process::exit(1);
+    }
+}
+
 fn deploy(args: DeployArgs) {
     // Load base and payer keypair
     let base_keypair = read_keypair_file(&args.base).expect("failed to read base keypair");
@@ -184,6 +301,7 @@ fn deploy(args: DeployArgs) {
     println!("Deployed {target}: {sig}");
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn deploy_with_max_program_len_with_seed(
     payer_address: &Pubkey,
     program_address: &Pubkey,
@@ -223,8 +341,9 @@ pub fn deploy_with_max_program_len_with_seed(
 }
 
 fn grind(mut args: GrindArgs) {
-    maybe_update_num_cpus(&mut args.num_cpus);
+    #[cfg(feature = "gpu")]
     let prefix = get_validated_prefix(&args);
+    #[cfg(feature = "gpu")]
     let suffix = get_validated_suffix(&args);
 
     // Initialize logger with optional logfile
@@ -239,20 +358,87 @@ fn grind(mut args: GrindArgs) {
     logger.timestamp_format("%Y-%m-%d %H:%M:%S");
     logger.level(Level::Info);
 
+    // Resolved once up front, so everything below grinds under it
+    if !args.base_chain.is_empty() {
+        args.base = args.effective_base().unwrap_or_else(|e| panic!("your {e}"));
+        args.base_chain.clear();
+        logfather::info!("grinding under derived base {}", args.base);
+    }
+
     // Print resource usage
+    maybe_update_num_cpus(&mut args.num_cpus);
+    args.continuous |= args.benchmark;
     logfather::info!("using {} threads", args.num_cpus);
     #[cfg(feature = "gpu")]
     logfather::info!("using {} gpus", args.num_gpus);
+    #[cfg(feature = "gpu")]
+    if args.charset.is_some() && args.num_gpus > 0 {
+        logfather::warn!("gpus generate their own seeds and ignore --charset");
+    }
+    #[cfg(feature = "gpu")]
+    if (args.seed_prefix.is_some() || args.seed_suffix.is_some()) && args.num_gpus > 0 {
+        logfather::warn!("gpus generate their own seeds and ignore --seed-prefix/--seed-suffix");
+    }
+    #[cfg(feature = "gpu")]
+    if args.worker.is_some() && args.num_gpus > 0 {
+        logfather::warn!("gpus generate their own seeds and ignore --worker, so may repeat other workers' seeds");
+    }
+    #[cfg(feature = "gpu")]
+    if !args.extra_bases.is_empty() && args.num_gpus > 0 {
+        logfather::warn!("gpus only grind under --base and ignore --also-base");
+    }
+    #[cfg(feature = "gpu")]
+    let byte_rules = args.pattern.is_some()
+        || !args.avoid_lookalikes.is_empty()
+        || args.leading_zeros.is_some()
+        || args.byte_sum.is_some()
+        || args.sorts_before.is_some()
+        || args.sorts_after.is_some();
+    #[cfg(feature = "gpu")]
+    if (args.matcher.is_some() || byte_rules) && args.num_gpus > 0 {
+        logfather::warn!("gpus only grind --prefix/--suffix and ignore --target/--pattern/--avoid-lookalike/--leading-zeros/--byte-sum/--sorts-*");
+    }
+
+    check_owner(&args.owner).unwrap_or_else(|e| panic!("your {e}"));
+    let matcher = args.matcher().unwrap_or_else(|e| panic!("your {e}"));
+    args.seed_affixes().unwrap_or_else(|e| panic!("your {e}"));
+    let matches = if args.continuous { args.max_matches.unwrap_or(1) } else { 1 };
+    args.check_seed_space(&matcher, matches).unwrap_or_else(|e| panic!("your {e}"));
+
+    // Estimate difficulty so we can tell when things look infeasible
+    let expected = matcher.expected_attempts().unwrap_or(f64::INFINITY);
+    logfather::info!(
+        "expecting ~{} attempts",
+        (expected as u64).to_formatted_string(&Locale::en)
+    );
+    #[cfg(not(feature = "gpu"))]
+    let workers = args.num_cpus;
+    #[cfg(feature = "gpu")]
+    let workers = args.num_cpus + args.num_gpus;
+    let watchdog: &'static Watchdog =
+        Box::leak(Box::new(Watchdog::new(expected, args.watchdog_multiple, workers)));
+    let pool: &'static Pool = Box::leak(Box::new(Pool::new(&args)));
+    // The bar shows the matches, and anything else would scroll it away
+    #[cfg(feature = "progress")]
+    if pool.progress.is_some() {
+        logger.terminal_ignore(Level::Info);
+    }
+    let started = Instant::now();
+    // The cpu threads keep to --max-secs themselves
+    #[cfg(feature = "gpu")]
+    let deadline = args.max_secs.map(|secs| Instant::now() + Duration::from_secs_f64(secs));
 
     #[cfg(feature = "gpu")]
     let _gpu_threads: Vec<_> = (0..args.num_gpus)
-        .map(move |gpu_index| {
+        .map(|gpu_index| {
+            let (address_out, seed_out) = (args.address_out.clone(), args.seed_out.clone());
             std::thread::Builder::new()
                 .name(format!("gpu{gpu_index}"))
                 .spawn(move || {
                     logfather::trace!("starting gpu {gpu_index}");
 
                     let mut out = [0; 24];
+                    let mut attempts = 0_u64;
                     for iteration in 0_u64.. {
                         // Exit if a thread found a solution
                         if EXIT.load(Ordering::SeqCst) {
@@ -270,14 +456,23 @@ fn grind(mut args: GrindArgs) {
 
                         // Reconstruct solution
                         let reconstructed: [u8; 32] = Sha256::new()
-                            .chain_update(&args.base)
+                            .chain_update(args.base)
                             .chain_update(&out[..16])
-                            .chain_update(&args.owner)
+                            .chain_update(args.owner)
                             .finalize()
                             .into();
                         let out_str = fd_bs58::encode_32(reconstructed);
                         let out_str_target_check = maybe_bs58_aware_lowercase(&out_str, args.case_insensitive);
-                        let count = u64::from_le_bytes(array::from_fn(|i| out[16 + i]));
+                        let count = u64::from_le_bytes(std::array::from_fn(|i| out[16 + i]));
+                        if watchdog.crossed(attempts, attempts + count) {
+                            logfather::warn!(
+                                "over {}x the expected attempts without a match; is the target feasible?",
+                                args.watchdog_multiple
+                            );
+                        }
+                        attempts += count;
+                        #[cfg(feature = "progress")]
+                        progress::ATTEMPTS.fetch_add(count, Ordering::Relaxed);
                         logfather::info!(
                             "{} found in {:.3} seconds on gpu {gpu_index:>3}; {:>13} iters; {:>12} iters/sec",
                             &out_str,
@@ -286,10 +481,26 @@ fn grind(mut args: GrindArgs) {
                             ((count as f64 / time_sec) as u64).to_formatted_string(&Locale::en)
                         );
 
-                        if out_str_target_check.starts_with(prefix) && out_str_target_check.ends_with(suffix) {
+                        if out_str_target_check.starts_with(prefix)
+                            && out_str_target_check.ends_with(suffix)
+                            // Not args.curve_ok, which would move all of args in
+                            && (!args.require_off_curve || !Pubkey::new_from_array(reconstructed).is_on_curve())
+                        {
+                            if !pool.claim(core::str::from_utf8(&out[..16]).unwrap()) {
+                                continue;
+                            }
                             logfather::info!("out seed = {out:?} -> {}", core::str::from_utf8(&out[..16]).unwrap());
+                            write_result_files(&address_out, &seed_out, &out_str, core::str::from_utf8(&out[..16]).unwrap());
+                            if pool.record(&args.base, &args.owner, &out_str, core::str::from_utf8(&out[..16]).unwrap(), count) {
+                                EXIT.store(true, Ordering::SeqCst);
+                                logfather::trace!("gpu thread {gpu_index} exiting");
+                                return;
+                            }
+                        }
+
+                        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                            logfather::info!("time budget reached");
                             EXIT.store(true, Ordering::SeqCst);
-                            logfather::trace!("gpu thread {gpu_index} exiting");
                             return;
                         }
                     }
@@ -299,111 +510,292 @@ fn grind(mut args: GrindArgs) {
         .collect();
 
     (0..args.num_cpus).into_par_iter().for_each(|i| {
-        let timer = Instant::now();
-        let mut count = 0_u64;
+        // This thread's attempts as of its last progress report, and as of
+        // its last match, which the watchdog counts from
+        let reported = Cell::new(0_u64);
+        let matched = Cell::new(0_u64);
+        let result = grind_thread(
+            &args,
+            i.into(),
+            args.num_cpus.into(),
+            |found| {
+                matched.set(matched.get() + found.attempts);
+                if !pool.claim(&found.seed) {
+                    return ControlFlow::Continue(());
+                }
+                let address = found.address.to_string();
+                let time_secs = found.elapsed.as_secs_f64();
+                logfather::info!(
+                    "cpu {i} found target: {address}; {:?} -> {} in {:.3}s; {} attempts; {} attempts per second",
+                    found.seed.as_bytes(),
+                    found.seed,
+                    time_secs,
+                    found.attempts.to_formatted_string(&Locale::en),
+                    ((found.attempts as f64 / time_secs) as u64).to_formatted_string(&Locale::en)
+                );
+                if !args.extra_bases.is_empty() {
+                    logfather::info!("cpu {i} match is under base {}", found.base);
+                }
+                write_result_files(&args.address_out, &args.seed_out, &address, &found.seed);
+
+                if pool.record(&found.base, &args.owner, &address, &found.seed, found.attempts) {
+                    EXIT.store(true, Ordering::Release);
+                    return ControlFlow::Break(());
+                }
+                ControlFlow::Continue(())
+            },
+            |&Progress { attempts, .. }| {
+                #[cfg(feature = "progress")]
+                progress::ATTEMPTS.fetch_add(attempts - reported.get(), Ordering::Relaxed);
+                let since_match = |attempts: u64| attempts.saturating_sub(matched.get());
+                if watchdog.crossed(since_match(reported.get()), since_match(attempts)) {
+                    logfather::warn!(
+                        "over {}x the expected attempts without a match; is the target feasible?",
+                        args.watchdog_multiple
+                    );
+                }
+                reported.set(attempts);
 
-        let base_sha = Sha256::new().chain_update(args.base);
-        loop {
-            if EXIT.load(Ordering::Acquire) {
-                return;
+                if EXIT.load(Ordering::Acquire) {
+                    return ControlFlow::Break(());
+                }
+                ControlFlow::Continue(())
+            },
+        );
+        match result {
+            Ok(()) => {}
+            // --max-secs is up
+            Err(GrindError::TimedOut(_)) => {
+                if !EXIT.swap(true, Ordering::AcqRel) {
+                    logfather::info!("time budget reached");
+                }
+            }
+            Err(e) => {
+                logfather::error!("{e}");
+                std::process::exit(1);
             }
+        }
+    });
 
-            let mut seed_iter = rand::thread_rng().sample_iter(&Alphanumeric).take(16);
-            let seed: [u8; 16] = array::from_fn(|_| seed_iter.next().unwrap());
+    let matches = pool.matches.load(Ordering::Acquire);
+    if args.continuous {
+        logfather::info!("collected {} matches", matches);
+    }
+    #[cfg(feature = "progress")]
+    if let Some(progress) = &pool.progress {
+        progress.finish(matches);
+    }
+    if args.benchmark {
+        let attempts = std::mem::take(&mut *pool.attempts.lock().unwrap());
+        let report = BenchmarkReport::new(started.elapsed(), args.num_cpus, expected, attempts);
+        println!("{}", serde_json::to_string(&report).unwrap());
+    }
+}
 
-            let pubkey_bytes: [u8; 32] = base_sha
-                .clone()
-                .chain_update(seed)
-                .chain_update(args.owner)
-                .finalize()
-                .into();
-            let pubkey = fd_bs58::encode_32(pubkey_bytes);
-            let out_str_target_check = maybe_bs58_aware_lowercase(&pubkey, args.case_insensitive);
+/// What --benchmark prints once its time is up
+#[derive(Serialize)]
+struct BenchmarkReport {
+    secs: f64,
+    threads: u32,
+    matches: usize,
+    matches_per_minute: f64,
+    /// Missing for targets that can't be estimated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected_attempts: Option<f64>,
+    /// Missing without any matches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attempts_per_match: Option<AttemptStats>,
+}
 
-            count += 1;
+/// The spread of attempts one thread took for each of its matches
+#[derive(Serialize)]
+struct AttemptStats {
+    min: u64,
+    mean: f64,
+    p50: u64,
+    p90: u64,
+    p99: u64,
+    max: u64,
+}
 
-            // Did cpu find target?
-            if out_str_target_check.starts_with(prefix) && out_str_target_check.ends_with(suffix) {
-                let time_secs = timer.elapsed().as_secs_f64();
-                logfather::info!(
-                    "cpu {i} found target: {pubkey}; {seed:?} -> {} in {:.3}s; {} attempts; {} attempts per second",
-                    core::str::from_utf8(&seed).unwrap(),
-                    time_secs,
-                    count.to_formatted_string(&Locale::en),
-                    ((count as f64 / time_secs) as u64).to_formatted_string(&Locale::en)
-                );
+impl BenchmarkReport {
+    fn new(
+        elapsed: Duration,
+        threads: u32,
+        expected: f64,
+        mut attempts: Vec<u64>,
+    ) -> BenchmarkReport {
+        let secs = elapsed.as_secs_f64();
+        attempts.sort_unstable();
+        let percentile = |p: usize| attempts[(attempts.len() - 1) * p / 100];
+        let attempts_per_match = (!attempts.is_empty()).then(|| AttemptStats {
+            min: attempts[0],
+            mean: attempts.iter().sum::<u64>() as f64 / attempts.len() as f64,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: attempts[attempts.len() - 1],
+        });
+        BenchmarkReport {
+            secs,
+            threads,
+            matches: attempts.len(),
+            matches_per_minute: attempts.len() as f64 * 60.0 / secs,
+            expected_attempts: expected.is_finite().then_some(expected),
+            attempts_per_match,
+        }
+    }
+}
+
+/// Decides when to stop after a match and, in continuous mode, collects
+/// every match as a json line so the file is usable even if we're killed
+struct Pool {
+    continuous: bool,
+    max_matches: Option<u64>,
+    matches: AtomicU64,
+    file: Option<Mutex<File>>,
+    /// Whether file records get a [`Metadata`]
+    metadata: bool,
+    /// Every seed matched so far in continuous mode, about 100 bytes
+    /// each counting the set's overhead, so 1M matches take ~100MB
+    seeds: Mutex<HashSet<String>>,
+    /// Attempts taken for each match, with --benchmark
+    benchmark: bool,
+    attempts: Mutex<Vec<u64>>,
+    /// Shows each match, unless falling back to logging them
+    #[cfg(feature = "progress")]
+    progress: Option<progress::Progress>,
+}
 
-                EXIT.store(true, Ordering::Release);
-                break;
+#[derive(Serialize)]
+struct PoolRecord<'a> {
+    address: &'a str,
+    seed: &'a str,
+    base: String,
+    owner: String,
+    attempts: u64,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    metadata: Option<Metadata>,
+}
+
+impl Pool {
+    fn new(args: &GrindArgs) -> Pool {
+        let file = args.pool_out.as_ref().map(|path| {
+            let open = || {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                File::options().create(true).append(true).open(path)
+            };
+            let file = open().unwrap_or_else(|e| {
+                panic!("failed to open pool file {}: {e}", path.display())
+            });
+            Mutex::new(file)
+        });
+
+        Pool {
+            continuous: args.continuous,
+            max_matches: args.max_matches,
+            matches: AtomicU64::new(0),
+            file,
+            metadata: args.pool_metadata,
+            seeds: Mutex::new(HashSet::new()),
+            benchmark: args.benchmark,
+            attempts: Mutex::new(Vec::new()),
+            #[cfg(feature = "progress")]
+            progress: progress::Progress::start(args.quiet),
+        }
+    }
+
+    /// Whether `seed` hasn't matched before, in which case the match may
+    /// be recorded. A repeat means the random seeds collided, which is
+    /// vanishingly rare for charsets [`GrindArgs::check_seed_space`]
+    /// allows, and is skipped so every match in a pool is distinct
+    fn claim(&self, seed: &str) -> bool {
+        if !self.continuous {
+            return true;
+        }
+        let new = self.seeds.lock().unwrap().insert(seed.to_string());
+        if !new {
+            logfather::warn!("seed {seed} matched twice, skipping the repeat");
+        }
+        new
+    }
+
+    /// Records a match, returning whether grinding should stop
+    fn record(&self, base: &Pubkey, owner: &Pubkey, address: &str, seed: &str, attempts: u64) -> bool {
+        if let Some(ref file) = self.file {
+            let record = PoolRecord {
+                address,
+                seed,
+                base: base.to_string(),
+                owner: owner.to_string(),
+                attempts,
+                metadata: self.metadata.then(Metadata::now),
+            };
+            let line = serde_json::to_string(&record).unwrap();
+
+            // One write per line so concurrent matches never interleave
+            let mut file = file.lock().unwrap();
+            if let Err(e) = writeln!(file, "{line}").and_then(|_| file.flush()) {
+                logfather::error!("failed to append to pool file: {e}");
+                logfather::error!("address = {address}, seed = {seed}");
+                std::process::exit(1);
             }
         }
-    });
-}
 
-fn get_validated_prefix(args: &GrindArgs) -> &'static str {
-    // Static string of BS58 characters
-    const BS58_CHARS: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
-
-    // Validate target (i.e. does it include 0, O, I, l)
-    //
-    // maybe TODO: technically we could accept I or o if case-insensitivity but I suspect
-    // most users will provide lowercase targets for case-insensitive searches
-
-    if let Some(ref prefix) = args.prefix {
-        for c in prefix.chars() {
-            assert!(
-                BS58_CHARS.contains(c),
-                "your prefix contains invalid bs58: {}",
-                c
-            );
+        if self.benchmark {
+            self.attempts.lock().unwrap().push(attempts);
+        }
+        let matches = self.matches.fetch_add(1, Ordering::AcqRel) + 1;
+        #[cfg(feature = "progress")]
+        if let Some(ref progress) = self.progress {
+            progress.found(address, seed, matches);
         }
-        let prefix = maybe_bs58_aware_lowercase(&prefix, args.case_insensitive);
-        return prefix.leak()
+        !self.continuous || self.max_matches.is_some_and(|max| matches >= max)
     }
-    ""
 }
 
-fn get_validated_suffix(args: &GrindArgs) -> &'static str {
-    // Static string of BS58 characters
-    const BS58_CHARS: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
-
-    // Validate target (i.e. does it include 0, O, I, l)
-    //
-    // maybe TODO: technically we could accept I or o if case-insensitivity but I suspect
-    // most users will provide lowercase targets for case-insensitive searches
-
-    if let Some(ref suffix) = args.suffix {
-        for c in suffix.chars() {
-            assert!(
-                BS58_CHARS.contains(c),
-                "your suffix contains invalid bs58: {}",
-                c
-            );
+/// Writes the address and seed to their requested files, if any. Exits
+/// the process on failure since the result would otherwise be lost.
+fn write_result_files(
+    address_out: &Option<PathBuf>,
+    seed_out: &Option<PathBuf>,
+    address: &str,
+    seed: &str,
+) {
+    let outputs = [(address_out, address), (seed_out, seed)];
+    for (path, contents) in outputs {
+        let Some(path) = path else { continue };
+        if let Err(e) = write_line(path, contents) {
+            logfather::error!("failed to write {}: {e}", path.display());
+            logfather::error!("address = {address}, seed = {seed}");
+            std::process::exit(1);
         }
-        let suffix = maybe_bs58_aware_lowercase(&suffix, args.case_insensitive);
-        return suffix.leak()
     }
-    ""
 }
 
-fn maybe_bs58_aware_lowercase(target: &str, case_insensitive: bool) -> String {
-    // L is only char that shouldn't be converted to lowercase in case-insensitivity case
-    const LOWERCASE_EXCEPTIONS: &str = "L";
-
-    if case_insensitive {
-        target
-            .chars()
-            .map(|c| {
-                if LOWERCASE_EXCEPTIONS.contains(c) {
-                    c
-                } else {
-                    c.to_ascii_lowercase()
-                }
-            })
-            .collect::<String>()
-    } else {
-        target.to_string()
+/// Writes `contents` and a trailing newline, creating parent dirs as needed
+fn write_line(path: &Path, contents: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
     }
+    std::fs::write(path, format!("{contents}\n"))
+}
+
+#[cfg(feature = "gpu")]
+fn get_validated_prefix(args: &GrindArgs) -> &'static str {
+    validated_target("prefix", &args.prefix, args.case_insensitive)
+        .unwrap_or_else(|e| panic!("your {e}"))
+        .leak()
+}
+
+#[cfg(feature = "gpu")]
+fn get_validated_suffix(args: &GrindArgs) -> &'static str {
+    let suffix = args.last4.clone().or(args.suffix.clone());
+    validated_target("suffix", &suffix, args.case_insensitive)
+        .unwrap_or_else(|e| panic!("your {e}"))
+        .leak()
 }
 
 extern "C" {
@@ -431,12 +823,34 @@ fn new_gpu_seed(gpu_id: u32, iteration: u64) -> [u8; 32] {
         .into()
 }
 
-fn parse_pubkey(input: &str) -> Result<Pubkey, String> {
-    Pubkey::from_str(input).map_err(|e| e.to_string())
-}
-
+/// 0 means every core. More threads than cores can't help, and the
+/// extra rayon jobs would never even start, so those are clamped
 fn maybe_update_num_cpus(num_cpus: &mut u32) {
+    let cores = rayon::current_num_threads() as u32;
     if *num_cpus == 0 {
-        *num_cpus = rayon::current_num_threads() as u32;
+        *num_cpus = cores;
+    } else if *num_cpus > cores {
+        logfather::warn!("--num-cpus {} is more than the {cores} cores available, using {cores}", *num_cpus);
+        *num_cpus = cores;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn num_cpus_is_clamped_to_the_cores() {
+        let cores = rayon::current_num_threads() as u32;
+        let clamped = |mut num_cpus| {
+            maybe_update_num_cpus(&mut num_cpus);
+            num_cpus
+        };
+        assert_eq!(clamped(0), cores);
+        for in_range in 1..=cores {
+            assert_eq!(clamped(in_range), in_range);
+        }
+        assert_eq!(clamped(cores + 1), cores);
+        assert_eq!(clamped(u32::MAX), cores);
     }
 }
@@ -0,0 +1,1045 @@
+use std::fmt;
+use std::str::FromStr;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Deserializer, Serialize};
+use solana_pubkey::Pubkey;
+
+use crate::{
+    base58::{is_base58_char, Aligned, PrefixFilter, MAX_ADDRESS_LEN},
+    confusable::Blocklist,
+    estimate, maybe_bs58_aware_lowercase,
+    grind::GrindError,
+    DEFAULT_PREFIX_FILTER_CHARS,
+};
+
+/// How the rules of a matcher combine
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Combine {
+    /// Every rule must match
+    #[default]
+    #[serde(alias = "and")]
+    All,
+    /// At least one rule must match
+    #[serde(alias = "or")]
+    Any,
+}
+
+/// A set of rules an address has to satisfy, e.g.
+///
+/// ```json
+/// {
+///     "combine": "all",
+///     "rules": [
+///         { "prefix": ["Sol", "Abc"], "case_insensitive": true },
+///         { "suffix": "Loop" }
+///     ],
+///     "avoid_lookalikes": ["PayPal"]
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct MatcherSpec {
+    #[serde(default)]
+    pub combine: Combine,
+    pub rules: Vec<RuleSpec>,
+    /// Rejects addresses that look like they contain any of these, see
+    /// [`Blocklist`], however the rules combine
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub avoid_lookalikes: Vec<String>,
+}
+
+/// A single rule, case-sensitive unless `case_insensitive` is set
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct RuleSpec {
+    #[serde(flatten)]
+    pub kind: RuleKind,
+    #[serde(default)]
+    pub case_insensitive: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    /// Starts with any of these; a single string is accepted too
+    Prefix(#[serde(deserialize_with = "one_or_many")] Vec<String>),
+    Suffix(String),
+    Contains(String),
+    Regex(String),
+    /// Starts with at least this many zero bytes, i.e. that many 1s
+    LeadingZeros(usize),
+    /// Sorts before this pubkey, comparing bytes
+    SortsBefore(String),
+    /// Sorts after this pubkey, comparing bytes
+    SortsAfter(String),
+    /// The sum of the address's bytes leaves this remainder
+    ByteSum(ByteSum),
+    /// Has these chars at the same positions from the start, where `?`
+    /// matches any char, e.g. "So?Lab"
+    Pattern(String),
+}
+
+/// A constraint on the sum of an address's 32 bytes, e.g. for sharding
+/// accounts into `modulus` buckets by address alone
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ByteSum {
+    /// Must be positive
+    pub modulus: u32,
+    /// Must be less than `modulus`, and at most [`ByteSum::MAX_SUM`]
+    #[serde(default)]
+    pub remainder: u32,
+}
+
+impl ByteSum {
+    /// What 32 bytes of 0xff add up to, so greater remainders never match
+    pub const MAX_SUM: u32 = 32 * 255;
+
+    #[inline]
+    fn is_match(&self, bytes: &[u8; 32]) -> bool {
+        bytes.iter().map(|&b| b as u32).sum::<u32>() % self.modulus == self.remainder
+    }
+}
+
+impl MatcherSpec {
+    /// The plain --prefix/--suffix target: both must match
+    pub fn from_target(
+        prefix: Option<&str>,
+        suffix: Option<&str>,
+        case_insensitive: bool,
+    ) -> MatcherSpec {
+        let prefix = prefix.map(|prefix| RuleKind::Prefix(vec![prefix.to_string()]));
+        let suffix = suffix.map(|suffix| RuleKind::Suffix(suffix.to_string()));
+        MatcherSpec {
+            combine: Combine::All,
+            rules: prefix
+                .into_iter()
+                .chain(suffix)
+                .map(|kind| RuleSpec {
+                    kind,
+                    case_insensitive,
+                })
+                .collect(),
+            avoid_lookalikes: Vec::new(),
+        }
+    }
+
+    /// Also requires `n` leading zero bytes, if set. Only makes sense
+    /// when combining with `all`
+    pub fn add_leading_zeros(&mut self, n: Option<usize>) {
+        self.rules.extend(n.map(|n| RuleSpec {
+            kind: RuleKind::LeadingZeros(n),
+            case_insensitive: false,
+        }));
+    }
+
+    /// Also requires sorting between these pubkeys, if set
+    pub fn add_sort_bounds(&mut self, before: Option<&Pubkey>, after: Option<&Pubkey>) {
+        let before = before.map(|before| RuleKind::SortsBefore(before.to_string()));
+        let after = after.map(|after| RuleKind::SortsAfter(after.to_string()));
+        self.rules.extend(before.into_iter().chain(after).map(|kind| RuleSpec {
+            kind,
+            case_insensitive: false,
+        }));
+    }
+
+    /// Also requires the bytes to sum to `byte_sum`'s remainder, if set.
+    /// Like [`MatcherSpec::add_leading_zeros`], this ANDs with the other
+    /// rules only when combining with `all`
+    pub fn add_byte_sum(&mut self, byte_sum: Option<ByteSum>) {
+        self.rules.extend(byte_sum.map(|byte_sum| RuleSpec {
+            kind: RuleKind::ByteSum(byte_sum),
+            case_insensitive: false,
+        }));
+    }
+
+    /// Also requires `pattern`'s chars at their positions, if set. Like
+    /// [`MatcherSpec::add_leading_zeros`], this ANDs with the other rules
+    /// only when combining with `all`
+    pub fn add_pattern(&mut self, pattern: Option<&str>, case_insensitive: bool) {
+        self.rules.extend(pattern.map(|pattern| RuleSpec {
+            kind: RuleKind::Pattern(pattern.to_string()),
+            case_insensitive,
+        }));
+    }
+
+    /// Whether any rule ignores case
+    pub fn case_insensitive(&self) -> bool {
+        self.rules.iter().any(|rule| rule.case_insensitive)
+    }
+
+    /// The same rules with every one ignoring case, e.g. to estimate how
+    /// much an exact-case target costs over relaxing it
+    pub fn ignoring_case(&self) -> MatcherSpec {
+        let mut spec = self.clone();
+        for rule in &mut spec.rules {
+            rule.case_insensitive = true;
+        }
+        spec
+    }
+}
+
+/// A validated [`MatcherSpec`], ready for the hot loop
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    combine: Combine,
+    rules: Vec<Rule>,
+    blocklist: Blocklist,
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    case_insensitive: bool,
+    kind: CompiledKind,
+    /// What it was compiled from, for [`Matcher::matched_rules`]
+    spec: RuleSpec,
+    /// Its index in the spec, since rules are reordered
+    index: usize,
+}
+
+#[derive(Clone, Debug)]
+enum CompiledKind {
+    /// Targets are lowercased already if case-insensitive
+    Prefix(Vec<(String, Option<PrefixFilter>)>),
+    Suffix(String),
+    Contains(String),
+    Regex(Regex),
+    LeadingZeros(usize),
+    SortsBefore([u8; 32]),
+    SortsAfter([u8; 32]),
+    ByteSum(ByteSum),
+    /// The pattern is lowercased already if case-insensitive. `fixed` are
+    /// its non-wildcard chars by position, and `filter` checks those
+    /// before the first wildcard on the bytes
+    Pattern {
+        pattern: String,
+        fixed: Vec<(usize, u8)>,
+        filter: Option<PrefixFilter>,
+    },
+}
+
+impl Matcher {
+    /// Validates `spec`, using the byte-level prefix check for up to
+    /// `prefix_filter_chars` chars of each prefix
+    pub fn new(spec: &MatcherSpec, prefix_filter_chars: usize) -> Result<Matcher, GrindError> {
+        let mut rules = spec
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| Rule::new(rule, index, prefix_filter_chars))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Cheapest first so short-circuiting skips the expensive rules
+        rules.sort_by_key(|rule| match rule.kind {
+            CompiledKind::LeadingZeros(_)
+            | CompiledKind::SortsBefore(_)
+            | CompiledKind::SortsAfter(_)
+            | CompiledKind::ByteSum(_) => 0,
+            CompiledKind::Prefix(_) | CompiledKind::Pattern { .. } => 1,
+            CompiledKind::Suffix(_) => 2,
+            CompiledKind::Contains(_) => 3,
+            CompiledKind::Regex(_) => 4,
+        });
+
+        Ok(Matcher {
+            combine: spec.combine,
+            rules,
+            blocklist: Blocklist::new(&spec.avoid_lookalikes)?,
+        })
+    }
+
+    /// Whether the address with these bytes satisfies the matcher
+    #[inline]
+    pub fn is_match(&self, pubkey: &[u8; 32]) -> bool {
+        let mut candidate = Candidate::new(pubkey);
+        let matched = match self.combine {
+            Combine::All => self.rules.iter().all(|rule| rule.is_match(&mut candidate)),
+            Combine::Any => self.rules.iter().any(|rule| rule.is_match(&mut candidate)),
+        };
+        // Only matches get this far, so encoding for it costs nothing
+        matched && !self.blocklist.is_blocked(candidate.encoded())
+    }
+
+    /// How close the address with these bytes comes to matching, in
+    /// matched chars out of [`Matcher::target_depth`]. This encodes every
+    /// address, so it's much slower than [`Matcher::is_match`].
+    pub fn depth(&self, pubkey: &[u8; 32]) -> usize {
+        let mut candidate = Candidate::new(pubkey);
+        let depths = self.rules.iter().map(|rule| rule.depth(&mut candidate));
+        match self.combine {
+            Combine::All => depths.sum(),
+            Combine::Any => depths.max().unwrap_or(0),
+        }
+    }
+
+    /// The rules, in spec order, that the address with these bytes
+    /// satisfies: all of them under [`Combine::All`], only the first under
+    /// [`Combine::Any`]. Each is narrowed to the one prefix that matched
+    /// when it lists several. Like [`Matcher::depth`] this encodes the
+    /// address, so it's for reporting on a match rather than finding one.
+    pub fn matched_rules(&self, pubkey: &[u8; 32]) -> Vec<RuleSpec> {
+        let mut candidate = Candidate::new(pubkey);
+        let mut matched: Vec<&Rule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.is_match(&mut candidate))
+            .collect();
+        matched.sort_by_key(|rule| rule.index);
+        if self.combine == Combine::Any {
+            matched.truncate(1);
+        }
+
+        matched
+            .into_iter()
+            .map(|rule| {
+                let mut spec = rule.spec.clone();
+                if let (CompiledKind::Prefix(compiled), RuleKind::Prefix(prefixes)) =
+                    (&rule.kind, &mut spec.kind)
+                {
+                    let folded = candidate.folded(rule.case_insensitive);
+                    let matched = compiled
+                        .iter()
+                        .position(|(prefix, _)| folded.starts_with(prefix.as_str()));
+                    if let Some(i) = matched {
+                        *prefixes = vec![prefixes.swap_remove(i)];
+                    }
+                }
+                spec
+            })
+            .collect()
+    }
+
+    /// The depth of a match, not counting regex rules which can't match
+    /// partially
+    pub fn target_depth(&self) -> usize {
+        let depths = self.rules.iter().map(Rule::target_depth);
+        match self.combine {
+            Combine::All => depths.sum(),
+            Combine::Any => depths.max().unwrap_or(0),
+        }
+    }
+
+    /// Expected attempts to find a match, if it can be estimated
+    pub fn expected_attempts(&self) -> Option<f64> {
+        let probabilities = self
+            .rules
+            .iter()
+            .map(Rule::probability)
+            .collect::<Option<Vec<f64>>>()?;
+
+        // Treats rules as independent, which is close enough
+        let probability = match self.combine {
+            Combine::All => probabilities.iter().product(),
+            Combine::Any => 1.0 - probabilities.iter().map(|p| 1.0 - p).product::<f64>(),
+        };
+        Some(1.0 / probability)
+    }
+}
+
+impl Rule {
+    fn new(spec: &RuleSpec, index: usize, prefix_filter_chars: usize) -> Result<Rule, GrindError> {
+        let case_insensitive = spec.case_insensitive;
+        let target = |which, target: &str| {
+            if let Some(c) = target.chars().find(|&c| !is_base58_char(c)) {
+                return Err(GrindError::InvalidTarget { which, c });
+            }
+            Ok(maybe_bs58_aware_lowercase(target, case_insensitive))
+        };
+
+        let kind = match &spec.kind {
+            RuleKind::Prefix(prefixes) => CompiledKind::Prefix(
+                prefixes
+                    .iter()
+                    .map(|prefix| {
+                        let prefix = target("prefix", prefix)?;
+                        let filter =
+                            PrefixFilter::new(&prefix, case_insensitive, prefix_filter_chars);
+                        Ok((prefix, filter))
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            RuleKind::Suffix(suffix) => CompiledKind::Suffix(target("suffix", suffix)?),
+            RuleKind::Contains(needle) => CompiledKind::Contains(target("contains", needle)?),
+            RuleKind::Regex(pattern) => CompiledKind::Regex(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(case_insensitive)
+                    .build()
+                    .map_err(|e| GrindError::InvalidRegex(e.to_string()))?,
+            ),
+            RuleKind::LeadingZeros(n) if *n > 32 => return Err(GrindError::InvalidLeadingZeros(*n)),
+            RuleKind::LeadingZeros(n) => CompiledKind::LeadingZeros(*n),
+            RuleKind::SortsBefore(reference) => CompiledKind::SortsBefore(reference_bytes(reference)?),
+            RuleKind::SortsAfter(reference) => CompiledKind::SortsAfter(reference_bytes(reference)?),
+            RuleKind::ByteSum(ByteSum { modulus, remainder })
+                if remainder >= modulus || *remainder > ByteSum::MAX_SUM =>
+            {
+                return Err(GrindError::InvalidByteSum {
+                    modulus: *modulus,
+                    remainder: *remainder,
+                })
+            }
+            RuleKind::ByteSum(byte_sum) => CompiledKind::ByteSum(*byte_sum),
+            RuleKind::Pattern(pattern) => {
+                let invalid =
+                    |why: &str| Err(GrindError::InvalidPattern(format!("{pattern:?} {why}")));
+                if let Some(c) = pattern.chars().find(|&c| c != '?' && !is_base58_char(c)) {
+                    return Err(GrindError::InvalidTarget { which: "pattern", c });
+                }
+                if pattern.len() > MAX_ADDRESS_LEN {
+                    return invalid("is longer than an address");
+                }
+                if pattern.chars().all(|c| c == '?') {
+                    return invalid("has no fixed chars");
+                }
+                let pattern = maybe_bs58_aware_lowercase(pattern, case_insensitive);
+                if estimate::pattern_probability(&pattern, case_insensitive) == 0.0 {
+                    return invalid("can never match");
+                }
+                let fixed = pattern
+                    .bytes()
+                    .enumerate()
+                    .filter(|&(_, c)| c != b'?')
+                    .collect();
+                let leading = &pattern[..pattern.find('?').unwrap_or(pattern.len())];
+                let filter = PrefixFilter::new(leading, case_insensitive, prefix_filter_chars);
+                CompiledKind::Pattern {
+                    pattern,
+                    fixed,
+                    filter,
+                }
+            }
+        };
+
+        Ok(Rule {
+            case_insensitive,
+            kind,
+            spec: spec.clone(),
+            index,
+        })
+    }
+
+    fn is_match(&self, candidate: &mut Candidate) -> bool {
+        match &self.kind {
+            CompiledKind::Prefix(prefixes) => prefixes.iter().any(|(prefix, filter)| {
+                filter.as_ref().is_none_or(|f| f.may_match(candidate.bytes))
+                    && candidate.folded(self.case_insensitive).starts_with(prefix.as_str())
+            }),
+            CompiledKind::Suffix(suffix) => candidate
+                .folded(self.case_insensitive)
+                .ends_with(suffix.as_str()),
+            CompiledKind::Contains(needle) => candidate
+                .folded(self.case_insensitive)
+                .contains(needle.as_str()),
+            // The regex handles case itself
+            CompiledKind::Regex(regex) => regex.is_match(candidate.encoded()),
+            // Each leading zero byte encodes to exactly one leading 1, so
+            // this never needs the encoding
+            CompiledKind::LeadingZeros(n) => candidate.bytes[..*n].iter().all(|&b| b == 0),
+            CompiledKind::SortsBefore(reference) => candidate.bytes < reference,
+            CompiledKind::SortsAfter(reference) => candidate.bytes > reference,
+            CompiledKind::ByteSum(byte_sum) => byte_sum.is_match(candidate.bytes),
+            CompiledKind::Pattern { fixed, filter, .. } => {
+                filter.as_ref().is_none_or(|f| f.may_match(candidate.bytes)) && {
+                    let folded = candidate.folded(self.case_insensitive).as_bytes();
+                    fixed.iter().all(|&(i, c)| folded.get(i) == Some(&c))
+                }
+            }
+        }
+    }
+
+    /// Matched chars of the best partial match
+    fn depth(&self, candidate: &mut Candidate) -> usize {
+        match &self.kind {
+            CompiledKind::Prefix(prefixes) => {
+                let folded = candidate.folded(self.case_insensitive);
+                prefixes
+                    .iter()
+                    .map(|(prefix, _)| common_len(folded.bytes(), prefix.bytes()))
+                    .max()
+                    .unwrap_or(0)
+            }
+            CompiledKind::Suffix(suffix) => {
+                let folded = candidate.folded(self.case_insensitive);
+                common_len(folded.bytes().rev(), suffix.bytes().rev())
+            }
+            // The longest start of the needle found anywhere
+            CompiledKind::Contains(needle) => {
+                let folded = candidate.folded(self.case_insensitive);
+                (1..=needle.len())
+                    .rev()
+                    .find(|&len| folded.contains(&needle[..len]))
+                    .unwrap_or(0)
+            }
+            CompiledKind::Regex(_)
+            | CompiledKind::SortsBefore(_)
+            | CompiledKind::SortsAfter(_)
+            | CompiledKind::ByteSum(_) => 0,
+            CompiledKind::LeadingZeros(n) => candidate.bytes.iter().take(*n).take_while(|&&b| b == 0).count(),
+            // Fixed chars in place, wherever they are
+            CompiledKind::Pattern { fixed, .. } => {
+                let folded = candidate.folded(self.case_insensitive).as_bytes();
+                fixed.iter().filter(|&&(i, c)| folded.get(i) == Some(&c)).count()
+            }
+        }
+    }
+
+    fn target_depth(&self) -> usize {
+        match &self.kind {
+            CompiledKind::Prefix(prefixes) => prefixes.iter().map(|(prefix, _)| prefix.len()).max().unwrap_or(0),
+            CompiledKind::Suffix(suffix) => suffix.len(),
+            CompiledKind::Contains(needle) => needle.len(),
+            CompiledKind::Regex(_)
+            | CompiledKind::SortsBefore(_)
+            | CompiledKind::SortsAfter(_)
+            | CompiledKind::ByteSum(_) => 0,
+            CompiledKind::LeadingZeros(n) => *n,
+            CompiledKind::Pattern { fixed, .. } => fixed.len(),
+        }
+    }
+
+    fn probability(&self) -> Option<f64> {
+        let ci = self.case_insensitive;
+        match &self.kind {
+            CompiledKind::Prefix(prefixes) => Some(
+                prefixes
+                    .iter()
+                    .map(|(prefix, _)| estimate::prefix_probability(prefix, ci))
+                    .sum::<f64>()
+                    .min(1.0),
+            ),
+            CompiledKind::Suffix(suffix) => Some(estimate::suffix_probability(suffix, ci)),
+            CompiledKind::Contains(needle) => Some(estimate::contains_probability(needle, ci)),
+            CompiledKind::Regex(_) => None,
+            CompiledKind::LeadingZeros(n) => Some(256_f64.powi(-(*n as i32))),
+            CompiledKind::SortsBefore(reference) => Some(fraction(reference)),
+            CompiledKind::SortsAfter(reference) => Some(1.0 - fraction(reference)),
+            // Close enough while the modulus is small next to the spread
+            // of sums, which is in the hundreds
+            CompiledKind::ByteSum(byte_sum) => Some(1.0 / byte_sum.modulus as f64),
+            CompiledKind::Pattern { pattern, .. } => Some(estimate::pattern_probability(pattern, ci)),
+        }
+    }
+}
+
+/// An address being tested, encoded (and lowercased) at most once and
+/// only if some rule needs it
+struct Candidate<'a> {
+    bytes: &'a [u8; 32],
+    encoded: Option<String>,
+    lowered: Option<String>,
+}
+
+impl<'a> Candidate<'a> {
+    fn new(bytes: &'a [u8; 32]) -> Candidate<'a> {
+        Candidate {
+            bytes,
+            encoded: None,
+            lowered: None,
+        }
+    }
+
+    /// fd_bs58's fixed-size encoder takes ~180ns a pubkey to the bs58
+    /// crate's ~1.7µs in release builds (benches/encode.rs), which every
+    /// candidate past the byte-level checks pays
+    fn encoded(&mut self) -> &str {
+        self.encoded
+            .get_or_insert_with(|| fd_bs58::encode_32(Aligned(*self.bytes)))
+    }
+
+    /// The address as compared by a rule with this case sensitivity
+    fn folded(&mut self, case_insensitive: bool) -> &str {
+        if !case_insensitive {
+            return self.encoded();
+        }
+        if self.lowered.is_none() {
+            let lowered = maybe_bs58_aware_lowercase(self.encoded(), true);
+            self.lowered = Some(lowered);
+        }
+        self.lowered.as_deref().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// Nothing to match on, e.g. `""`, `"~"` or `"*"`
+    Empty,
+    /// More than one unescaped `*`
+    MultipleWildcards,
+    /// A `\` that isn't followed by `*`, `~` or `\` (None at the end)
+    InvalidEscape(Option<char>),
+    /// The parts parsed but aren't a valid target
+    Target(GrindError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "target is empty"),
+            ParseError::MultipleWildcards => write!(f, "target has more than one *"),
+            ParseError::InvalidEscape(Some(c)) => write!(f, "invalid escape: \\{c}"),
+            ParseError::InvalidEscape(None) => write!(f, "target ends with a lone \\"),
+            ParseError::Target(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a compact target into a validated matcher, see
+/// [`parse_target_spec`] for the grammar
+pub fn parse_target(spec: &str) -> Result<Matcher, ParseError> {
+    let spec = parse_target_spec(spec)?;
+    Matcher::new(&spec, DEFAULT_PREFIX_FILTER_CHARS).map_err(ParseError::Target)
+}
+
+/// Parses a compact target:
+///
+/// ```text
+/// target = ["~"] chars ["*" chars]
+/// ```
+///
+/// - `Sol*Loop` starts with Sol and ends with Loop
+/// - `Sol*` starts with Sol, `*Loop` ends with Loop
+/// - `abc` (no `*`) contains abc anywhere
+/// - a leading `~` makes every part case-insensitive, e.g. `~abc`
+/// - `\` escapes `*`, `~` and `\`
+///
+/// Empty parts are left out, but at least one part must be non-empty.
+/// Only the syntax is checked here; characters are validated when the
+/// spec is turned into a [`Matcher`].
+pub fn parse_target_spec(spec: &str) -> Result<MatcherSpec, ParseError> {
+    let (case_insensitive, spec) = match spec.strip_prefix('~') {
+        Some(rest) => (true, rest),
+        None => (false, spec),
+    };
+
+    // Split on unescaped *s, unescaping as we go
+    let mut parts = vec![String::new()];
+    let mut chars = spec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped @ ('*' | '~' | '\\')) => parts.last_mut().unwrap().push(escaped),
+                other => return Err(ParseError::InvalidEscape(other)),
+            },
+            '*' => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    let kinds: Vec<RuleKind> = match parts.as_slice() {
+        [needle] => vec![RuleKind::Contains(needle.clone())],
+        [prefix, suffix] => [
+            (!prefix.is_empty()).then(|| RuleKind::Prefix(vec![prefix.clone()])),
+            (!suffix.is_empty()).then(|| RuleKind::Suffix(suffix.clone())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+        _ => return Err(ParseError::MultipleWildcards),
+    };
+    if parts.iter().all(String::is_empty) {
+        return Err(ParseError::Empty);
+    }
+
+    Ok(MatcherSpec {
+        combine: Combine::All,
+        rules: kinds
+            .into_iter()
+            .map(|kind| RuleSpec {
+                kind,
+                case_insensitive,
+            })
+            .collect(),
+        avoid_lookalikes: Vec::new(),
+    })
+}
+
+/// The bytes of a sort bound pubkey
+fn reference_bytes(reference: &str) -> Result<[u8; 32], GrindError> {
+    Pubkey::from_str(reference)
+        .map(|pubkey| pubkey.to_bytes())
+        .map_err(|_| GrindError::InvalidReference(reference.to_string()))
+}
+
+/// Where `bytes` falls between all zeros (0) and all ones (1)
+fn fraction(bytes: &[u8; 32]) -> f64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0.0, |fraction, &byte| (fraction + byte as f64) / 256.0)
+}
+
+/// Length of the common start of `a` and `b`
+fn common_len(a: impl Iterator<Item = u8>, b: impl Iterator<Item = u8>) -> usize {
+    a.zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// Accepts either a single string or a list of them
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(one) => vec![one],
+        OneOrMany::Many(many) => many,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WRAPPED_SOL: &str = "So11111111111111111111111111111111111111112";
+    const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    fn bytes(address: &str) -> [u8; 32] {
+        Pubkey::from_str(address).unwrap().to_bytes()
+    }
+
+    fn rule(kind: RuleKind, case_insensitive: bool) -> RuleSpec {
+        RuleSpec {
+            kind,
+            case_insensitive,
+        }
+    }
+
+    fn matcher(combine: Combine, rules: Vec<RuleSpec>) -> Matcher {
+        let spec = MatcherSpec {
+            combine,
+            rules,
+            avoid_lookalikes: Vec::new(),
+        };
+        Matcher::new(&spec, DEFAULT_PREFIX_FILTER_CHARS).unwrap()
+    }
+
+    fn prefixes(prefixes: &[&str]) -> RuleKind {
+        RuleKind::Prefix(prefixes.iter().map(|prefix| prefix.to_string()).collect())
+    }
+
+    #[test]
+    fn any_of_prefix_ignoring_case_and_exact_suffix() {
+        let brand = |suffix: &str| {
+            matcher(
+                Combine::All,
+                vec![
+                    rule(prefixes(&["so1", "tok"]), true),
+                    rule(RuleKind::Suffix(suffix.to_string()), false),
+                ],
+            )
+        };
+        assert!(brand("5DA").is_match(&bytes(TOKEN_PROGRAM)));
+        assert!(!brand("5da").is_match(&bytes(TOKEN_PROGRAM)));
+        // The prefix matches in any case, but the suffix doesn't
+        assert!(!brand("5DA").is_match(&bytes(WRAPPED_SOL)));
+    }
+
+    #[test]
+    fn each_rule_keeps_its_own_case() {
+        for (kind, matches_exact) in [
+            (prefixes(&["token"]), false),
+            (RuleKind::Suffix("vq5da".to_string()), false),
+            (RuleKind::Contains("KEGQ".to_string()), false),
+            (RuleKind::Contains("kegQ".to_string()), true),
+            (RuleKind::Regex("^tok.*5da$".to_string()), false),
+            (RuleKind::Regex("^Tok.*5DA$".to_string()), true),
+        ] {
+            let exact = matcher(Combine::All, vec![rule(kind.clone(), false)]);
+            let relaxed = matcher(Combine::All, vec![rule(kind.clone(), true)]);
+            assert_eq!(exact.is_match(&bytes(TOKEN_PROGRAM)), matches_exact, "{kind:?}");
+            assert!(relaxed.is_match(&bytes(TOKEN_PROGRAM)), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn all_needs_every_rule_and_any_needs_one() {
+        let rules = vec![
+            rule(RuleKind::Contains("kegQ".to_string()), false),
+            rule(RuleKind::Regex("112$".to_string()), false),
+        ];
+        let all = matcher(Combine::All, rules.clone());
+        let any = matcher(Combine::Any, rules);
+        for address in [WRAPPED_SOL, TOKEN_PROGRAM] {
+            assert!(!all.is_match(&bytes(address)), "{address}");
+            assert!(any.is_match(&bytes(address)), "{address}");
+        }
+        assert!(!any.is_match(&bytes("11111111111111111111111111111111")));
+    }
+
+    #[test]
+    fn any_expects_fewer_attempts_than_all() {
+        let rules = vec![
+            rule(prefixes(&["ab"]), false),
+            rule(RuleKind::Suffix("cd".to_string()), true),
+        ];
+        let all = matcher(Combine::All, rules.clone()).expected_attempts().unwrap();
+        let any = matcher(Combine::Any, rules).expected_attempts().unwrap();
+        assert!(any < all, "any {any} all {all}");
+    }
+
+    fn parsed(spec: &str) -> Vec<RuleSpec> {
+        let parsed = parse_target_spec(spec).unwrap();
+        assert_eq!(parsed.combine, Combine::All, "{spec}");
+        parsed.rules
+    }
+
+    #[test]
+    fn target_spec_parts() {
+        let exact = |kind| rule(kind, false);
+        assert_eq!(
+            parsed("Sol*Loop"),
+            [exact(prefixes(&["Sol"])), exact(RuleKind::Suffix("Loop".to_string()))]
+        );
+        assert_eq!(parsed("*Loop"), [exact(RuleKind::Suffix("Loop".to_string()))]);
+        assert_eq!(parsed("Sol*"), [exact(prefixes(&["Sol"]))]);
+        assert_eq!(parsed("abc"), [exact(RuleKind::Contains("abc".to_string()))]);
+        assert_eq!(parsed("~abc"), [rule(RuleKind::Contains("abc".to_string()), true)]);
+        assert_eq!(
+            parsed("~ab*c"),
+            [rule(prefixes(&["ab"]), true), rule(RuleKind::Suffix("c".to_string()), true)]
+        );
+    }
+
+    #[test]
+    fn target_spec_escapes() {
+        let contains = |needle: &str| [rule(RuleKind::Contains(needle.to_string()), false)];
+        assert_eq!(parsed(r"\*"), contains("*"));
+        assert_eq!(parsed(r"a\*b"), contains("a*b"));
+        assert_eq!(parsed(r"\~a"), contains("~a"));
+        assert_eq!(parsed(r"a\\b"), contains(r"a\b"));
+    }
+
+    #[test]
+    fn target_spec_errors() {
+        assert_eq!(parse_target_spec("a**b"), Err(ParseError::MultipleWildcards));
+        assert_eq!(parse_target_spec("a*b*c"), Err(ParseError::MultipleWildcards));
+        assert_eq!(parse_target_spec("abc\\"), Err(ParseError::InvalidEscape(None)));
+        assert_eq!(parse_target_spec(r"a\b"), Err(ParseError::InvalidEscape(Some('b'))));
+        for empty in ["", "~", "*", "~*"] {
+            assert_eq!(parse_target_spec(empty), Err(ParseError::Empty), "{empty:?}");
+        }
+        // Only the full parse checks the chars
+        assert!(parse_target_spec("0*").is_ok());
+        assert!(matches!(
+            parse_target("0*"),
+            Err(ParseError::Target(GrindError::InvalidTarget { which: "prefix", c: '0' }))
+        ));
+    }
+
+    #[test]
+    fn leading_zero_bytes_are_leading_ones() {
+        for n in 0..=32 {
+            let mut bytes = [0xa5; 32];
+            bytes[..n].fill(0);
+            let encoded = bs58::encode(bytes).into_string();
+            let ones = encoded.chars().take_while(|&c| c == '1').count();
+            assert_eq!(ones, n, "{encoded}");
+
+            let zeros = |n| matcher(Combine::All, vec![rule(RuleKind::LeadingZeros(n), false)]);
+            assert!(zeros(n).is_match(&bytes), "{encoded}");
+            if n < 32 {
+                assert!(!zeros(n + 1).is_match(&bytes), "{encoded}");
+            }
+        }
+        let spec = MatcherSpec {
+            rules: vec![rule(RuleKind::LeadingZeros(33), false)],
+            ..MatcherSpec::default()
+        };
+        assert!(matches!(
+            Matcher::new(&spec, DEFAULT_PREFIX_FILTER_CHARS),
+            Err(GrindError::InvalidLeadingZeros(33))
+        ));
+    }
+
+    #[test]
+    fn sort_bounds_compare_bytes_strictly() {
+        let mut middle = [0; 32];
+        middle[0] = 0x80;
+        let reference = Pubkey::new_from_array(middle).to_string();
+        let bound = |kind| matcher(Combine::All, vec![rule(kind, false)]);
+        let before = bound(RuleKind::SortsBefore(reference.clone()));
+        let after = bound(RuleKind::SortsAfter(reference));
+
+        let mut just_below = [0xff; 32];
+        just_below[0] = 0x7f;
+        let mut just_above = middle;
+        just_above[31] = 1;
+        for (bytes, is_before, is_after) in [
+            ([0; 32], true, false),
+            (just_below, true, false),
+            (middle, false, false),
+            (just_above, false, true),
+            ([0xff; 32], false, true),
+        ] {
+            assert_eq!(before.is_match(&bytes), is_before, "{bytes:?}");
+            assert_eq!(after.is_match(&bytes), is_after, "{bytes:?}");
+        }
+        // Half of all addresses fall on either side
+        assert_eq!(before.expected_attempts(), Some(2.0));
+        assert_eq!(after.expected_attempts(), Some(2.0));
+    }
+
+    #[test]
+    fn sort_bounds_between_two_references() {
+        let (low, high) = (WRAPPED_SOL, TOKEN_PROGRAM);
+        let between = matcher(
+            Combine::All,
+            vec![
+                rule(RuleKind::SortsAfter(low.to_string()), false),
+                rule(RuleKind::SortsBefore(high.to_string()), false),
+            ],
+        );
+        assert!(!between.is_match(&bytes(low)));
+        assert!(!between.is_match(&bytes(high)));
+        let mut inside = bytes(low);
+        inside[31] = inside[31].wrapping_add(1);
+        assert!(between.is_match(&inside));
+
+        let spec = MatcherSpec {
+            rules: vec![rule(RuleKind::SortsBefore("not a pubkey".to_string()), false)],
+            ..MatcherSpec::default()
+        };
+        assert!(matches!(
+            Matcher::new(&spec, DEFAULT_PREFIX_FILTER_CHARS),
+            Err(GrindError::InvalidReference(reference)) if reference == "not a pubkey"
+        ));
+    }
+
+    #[test]
+    fn byte_sum_remainders_must_be_reachable() {
+        let byte_sum = |modulus, remainder| {
+            let spec = MatcherSpec {
+                rules: vec![rule(RuleKind::ByteSum(ByteSum { modulus, remainder }), false)],
+                ..MatcherSpec::default()
+            };
+            Matcher::new(&spec, DEFAULT_PREFIX_FILTER_CHARS)
+        };
+        // Only all 0xff bytes reach the largest sum
+        let largest = byte_sum(ByteSum::MAX_SUM + 1, ByteSum::MAX_SUM).unwrap();
+        assert!(largest.is_match(&[0xff; 32]));
+        let mut short = [0xff; 32];
+        short[7] = 0xfe;
+        assert!(!largest.is_match(&short));
+        let mut three = [0; 32];
+        three[2] = 3;
+        assert!(byte_sum(16, 3).unwrap().is_match(&three));
+        assert!(!byte_sum(16, 4).unwrap().is_match(&three));
+
+        for (modulus, remainder) in [
+            (0, 0),
+            (16, 16),
+            (16, 17),
+            (ByteSum::MAX_SUM + 2, ByteSum::MAX_SUM + 1),
+            (u32::MAX, u32::MAX - 1),
+        ] {
+            assert_eq!(
+                byte_sum(modulus, remainder).unwrap_err(),
+                GrindError::InvalidByteSum { modulus, remainder },
+                "{remainder} mod {modulus}"
+            );
+        }
+    }
+
+    fn pattern(pattern: &str, case_insensitive: bool) -> Result<Matcher, GrindError> {
+        let spec = MatcherSpec {
+            rules: vec![rule(RuleKind::Pattern(pattern.to_string()), case_insensitive)],
+            ..MatcherSpec::default()
+        };
+        Matcher::new(&spec, DEFAULT_PREFIX_FILTER_CHARS)
+    }
+
+    #[test]
+    fn patterns_match_chars_at_their_positions() {
+        // The last char of each, with everything before it a wildcard
+        let last = |address: &str| "?".repeat(address.len() - 1) + &address[address.len() - 1..];
+        for (spec, case_insensitive, matches_sol, matches_token) in [
+            ("So?1", false, true, false),
+            ("?o1", false, true, false),
+            ("?o", false, true, true),
+            ("Tok?nkeg", false, false, true),
+            ("tok?nkeg", false, false, false),
+            ("tok?nkeg", true, false, true),
+            ("????????Q", false, false, true),
+            ("?????????F", true, false, true),
+            ("?????????F", false, false, false),
+            (&last(WRAPPED_SOL), false, true, false),
+            (&last(TOKEN_PROGRAM), false, false, true),
+        ] {
+            let matcher = pattern(spec, case_insensitive).unwrap();
+            assert_eq!(matcher.is_match(&bytes(WRAPPED_SOL)), matches_sol, "{spec}");
+            assert_eq!(matcher.is_match(&bytes(TOKEN_PROGRAM)), matches_token, "{spec}");
+        }
+        // Partial matches count the fixed chars in place
+        let partial = pattern("So?Lab", false).unwrap();
+        assert_eq!(partial.depth(&bytes(WRAPPED_SOL)), 2);
+        assert_eq!(partial.target_depth(), 5);
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert_eq!(
+            pattern("So?0", false).unwrap_err(),
+            GrindError::InvalidTarget { which: "pattern", c: '0' }
+        );
+        for invalid in ["", "???", &"a".repeat(MAX_ADDRESS_LEN + 1)] {
+            assert!(
+                matches!(pattern(invalid, false), Err(GrindError::InvalidPattern(_))),
+                "{invalid:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn matched_rules_name_the_rules_and_prefixes() {
+        let any = matcher(
+            Combine::Any,
+            vec![
+                rule(prefixes(&["Abc", "so1", "Xyz"]), true),
+                rule(RuleKind::Suffix("5DA".to_string()), false),
+            ],
+        );
+        // Narrowed to the prefix that matched, keeping the spec's case
+        assert_eq!(
+            any.matched_rules(&bytes(WRAPPED_SOL)),
+            [rule(prefixes(&["so1"]), true)]
+        );
+        assert_eq!(
+            any.matched_rules(&bytes(TOKEN_PROGRAM)),
+            [rule(RuleKind::Suffix("5DA".to_string()), false)]
+        );
+        assert_eq!(any.matched_rules(&bytes("11111111111111111111111111111111")), []);
+
+        // When several match, the first in the spec wins, though the
+        // prefix is checked first
+        let either = matcher(
+            Combine::Any,
+            vec![
+                rule(RuleKind::Suffix("112".to_string()), false),
+                rule(prefixes(&["So"]), false),
+            ],
+        );
+        assert_eq!(
+            either.matched_rules(&bytes(WRAPPED_SOL)),
+            [rule(RuleKind::Suffix("112".to_string()), false)]
+        );
+
+        // Unless they all had to, in which case they're all reported
+        let both = matcher(
+            Combine::All,
+            vec![
+                rule(RuleKind::Suffix("112".to_string()), false),
+                rule(prefixes(&["Xyz", "So"]), false),
+            ],
+        );
+        assert_eq!(
+            both.matched_rules(&bytes(WRAPPED_SOL)),
+            [
+                rule(RuleKind::Suffix("112".to_string()), false),
+                rule(prefixes(&["So"]), false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use std::sync::OnceLock;
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+
+/// When, where and by what a result record was made, so pools gathered
+/// across runs and machines describe themselves
+#[derive(Serialize, Clone, Debug)]
+pub struct Metadata {
+    /// RFC 3339 in UTC, e.g. 2024-05-01T12:00:00Z
+    pub generated_at: String,
+    pub hostname: String,
+    /// This crate's version
+    pub version: &'static str,
+}
+
+impl Metadata {
+    /// Metadata for a record made now, on this machine
+    pub fn now() -> Metadata {
+        static HOSTNAME: OnceLock<String> = OnceLock::new();
+        let hostname =
+            HOSTNAME.get_or_init(|| gethostname::gethostname().to_string_lossy().into_owned());
+        Metadata {
+            generated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            hostname: hostname.clone(),
+            version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+}
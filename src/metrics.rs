@@ -0,0 +1,162 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Why a request was turned away, as reported by /metrics
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// The body didn't parse or fit the schema
+    InvalidBody,
+    InvalidBase,
+    InvalidOwner,
+    /// Both a matcher and prefix/suffix were given
+    ConflictingTarget,
+    /// A target char isn't base58
+    InvalidTargetChar,
+    InvalidRegex,
+    InvalidLeadingZeros,
+    InvalidLast4,
+    /// A sort bound isn't a pubkey
+    InvalidReference,
+    InvalidByteSum,
+    InvalidPattern,
+    InvalidLookalike,
+    InvalidSeed,
+    /// So rare the seeds would run out before enough of them match
+    InfeasibleTarget,
+    /// A callback_url isn't allowed, or callbacks aren't supported
+    InvalidCallback,
+    ShuttingDown,
+    Unauthorized,
+    /// No running job has the id
+    UnknownJob,
+    /// The request or its body took too long
+    Timeout,
+    /// Too many requests were already waiting for a grind slot
+    QueueFull,
+    GrindFailed,
+    /// No match within --grind-timeout-secs
+    GrindTimeout,
+}
+
+impl Reason {
+    /// In declaration order, so `reason as usize` indexes it
+    pub const ALL: [Reason; 22] = [
+        Reason::InvalidBody,
+        Reason::InvalidBase,
+        Reason::InvalidOwner,
+        Reason::ConflictingTarget,
+        Reason::InvalidTargetChar,
+        Reason::InvalidRegex,
+        Reason::InvalidLeadingZeros,
+        Reason::InvalidLast4,
+        Reason::InvalidReference,
+        Reason::InvalidByteSum,
+        Reason::InvalidPattern,
+        Reason::InvalidLookalike,
+        Reason::InvalidSeed,
+        Reason::InfeasibleTarget,
+        Reason::InvalidCallback,
+        Reason::ShuttingDown,
+        Reason::Unauthorized,
+        Reason::UnknownJob,
+        Reason::Timeout,
+        Reason::QueueFull,
+        Reason::GrindFailed,
+        Reason::GrindTimeout,
+    ];
+
+    /// The `reason` label value
+    pub fn label(self) -> &'static str {
+        match self {
+            Reason::InvalidBody => "invalid_body",
+            Reason::InvalidBase => "invalid_base",
+            Reason::InvalidOwner => "invalid_owner",
+            Reason::ConflictingTarget => "conflicting_target",
+            Reason::InvalidTargetChar => "invalid_target_char",
+            Reason::InvalidRegex => "invalid_regex",
+            Reason::InvalidLeadingZeros => "invalid_leading_zeros",
+            Reason::InvalidLast4 => "invalid_last4",
+            Reason::InvalidReference => "invalid_reference",
+            Reason::InvalidByteSum => "invalid_byte_sum",
+            Reason::InvalidPattern => "invalid_pattern",
+            Reason::InvalidLookalike => "invalid_lookalike",
+            Reason::InvalidSeed => "invalid_seed",
+            Reason::InfeasibleTarget => "infeasible_target",
+            Reason::InvalidCallback => "invalid_callback",
+            Reason::ShuttingDown => "shutting_down",
+            Reason::Unauthorized => "unauthorized",
+            Reason::UnknownJob => "unknown_job",
+            Reason::Timeout => "timeout",
+            Reason::QueueFull => "queue_full",
+            Reason::GrindFailed => "grind_failed",
+            Reason::GrindTimeout => "grind_timeout",
+        }
+    }
+}
+
+/// Counters served at /metrics in the prometheus text format
+#[derive(Default)]
+pub struct Metrics {
+    generated: AtomicU64,
+    rejected: [AtomicU64; Reason::ALL.len()],
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+impl Metrics {
+    pub fn generated(&self) {
+        self.generated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rejected(&self, reason: Reason) {
+        self.rejected[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cache_miss(&self) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders every counter, including the in-flight and queue gauges
+    /// owned by the server state
+    pub fn render(&self, in_flight: usize, queued: usize) -> String {
+        let mut out = String::new();
+        counter(&mut out, "vanity_generated_total", "Addresses generated");
+        writeln!(out, "vanity_generated_total {}", self.generated.load(Ordering::Relaxed)).unwrap();
+
+        counter(&mut out, "vanity_requests_rejected_total", "Requests rejected, by reason");
+        for reason in Reason::ALL {
+            writeln!(
+                out,
+                "vanity_requests_rejected_total{{reason=\"{}\"}} {}",
+                reason.label(),
+                self.rejected[reason as usize].load(Ordering::Relaxed)
+            )
+            .unwrap();
+        }
+
+        let hits = self.cache_hits.load(Ordering::Relaxed);
+        counter(&mut out, "vanity_result_cache_hits_total", "Requests answered from the result cache");
+        writeln!(out, "vanity_result_cache_hits_total {hits}").unwrap();
+        let misses = self.cache_misses.load(Ordering::Relaxed);
+        counter(&mut out, "vanity_result_cache_misses_total", "Requests the cache couldn't answer");
+        writeln!(out, "vanity_result_cache_misses_total {misses}").unwrap();
+
+        writeln!(out, "# HELP vanity_requests_in_flight Generate requests being handled").unwrap();
+        writeln!(out, "# TYPE vanity_requests_in_flight gauge").unwrap();
+        writeln!(out, "vanity_requests_in_flight {in_flight}").unwrap();
+
+        writeln!(out, "# HELP vanity_grind_queue_depth Requests waiting for a grind slot").unwrap();
+        writeln!(out, "# TYPE vanity_grind_queue_depth gauge").unwrap();
+        writeln!(out, "vanity_grind_queue_depth {queued}").unwrap();
+        out
+    }
+}
+
+fn counter(out: &mut String, name: &str, help: &str) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} counter").unwrap();
+}
@@ -0,0 +1,57 @@
+//! Programs that commonly own seeded accounts, e.g. for
+//! [`GrindArgs::owner`](crate::GrindArgs::owner)
+
+use solana_pubkey::{pubkey, Pubkey};
+
+pub const SYSTEM_PROGRAM: Pubkey = pubkey!("11111111111111111111111111111111");
+/// The original SPL Token program
+pub const TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const STAKE_PROGRAM: Pubkey = pubkey!("Stake11111111111111111111111111111111111111");
+pub const ASSOCIATED_TOKEN_PROGRAM: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Every program above by the name [`by_name`] knows it by
+pub const NAMED: [(&str, Pubkey); 5] = [
+    ("system", SYSTEM_PROGRAM),
+    ("token", TOKEN_PROGRAM),
+    ("token-2022", TOKEN_2022_PROGRAM),
+    ("stake", STAKE_PROGRAM),
+    ("associated-token", ASSOCIATED_TOKEN_PROGRAM),
+];
+
+/// The program called `name` in [`NAMED`], ignoring case
+pub fn by_name(name: &str) -> Option<Pubkey> {
+    NAMED
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map(|(_, program)| *program)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn programs_are_their_canonical_ids() {
+        for (program, id) in [
+            (SYSTEM_PROGRAM, "11111111111111111111111111111111"),
+            (TOKEN_PROGRAM, "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+            (TOKEN_2022_PROGRAM, "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"),
+            (STAKE_PROGRAM, "Stake11111111111111111111111111111111111111"),
+            (ASSOCIATED_TOKEN_PROGRAM, "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"),
+        ] {
+            assert_eq!(program, Pubkey::from_str(id).unwrap(), "{id}");
+        }
+    }
+
+    #[test]
+    fn names_ignore_case() {
+        for (name, program) in NAMED {
+            assert_eq!(by_name(name), Some(program));
+            assert_eq!(by_name(&name.to_uppercase()), Some(program));
+        }
+        assert_eq!(by_name("token2022"), None);
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use indicatif::{HumanCount, ProgressBar, ProgressState, ProgressStyle};
+
+/// Attempts across every cpu and gpu thread, as of their last stop check
+pub static ATTEMPTS: AtomicU64 = AtomicU64::new(0);
+
+/// How often the bar catches up with [`ATTEMPTS`]
+const REFRESH: Duration = Duration::from_millis(250);
+
+/// A live spinner on stderr showing the attempts so far, their rate and
+/// the time taken, with each match printed above it
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    /// Starts drawing, unless stderr isn't a terminal (or `quiet`), in
+    /// which case the caller should keep logging instead
+    pub fn start(quiet: bool) -> Option<Progress> {
+        if quiet || !std::io::stderr().is_terminal() {
+            return None;
+        }
+        let style = ProgressStyle::with_template(
+            "{spinner} [{elapsed_precise}] {human_pos} attempts, {rate} {msg}",
+        )
+        .unwrap()
+        // {per_sec} shows fractions of an attempt
+        .with_key("rate", |state: &ProgressState, w: &mut dyn fmt::Write| {
+            write!(w, "{}/s", HumanCount(state.per_sec() as u64)).unwrap()
+        });
+        let bar = ProgressBar::new_spinner().with_style(style);
+
+        let updater = bar.clone();
+        std::thread::Builder::new()
+            .name("progress".to_string())
+            .spawn(move || {
+                while !updater.is_finished() {
+                    updater.set_position(ATTEMPTS.load(Ordering::Relaxed));
+                    std::thread::sleep(REFRESH);
+                }
+            })
+            .unwrap();
+        Some(Progress { bar })
+    }
+
+    /// Prints a match without disturbing the bar, which then counts it
+    pub fn found(&self, address: &str, seed: &str, matches: u64) {
+        self.bar.println(format!("found {address} with seed {seed}"));
+        self.bar.set_message(matches_message(matches));
+    }
+
+    /// Stops the bar, leaving it up with the final counts
+    pub fn finish(&self, matches: u64) {
+        self.bar.set_position(ATTEMPTS.load(Ordering::Relaxed));
+        self.bar.finish_with_message(matches_message(matches));
+    }
+}
+
+fn matches_message(matches: u64) -> String {
+    match matches {
+        0 => "(no match)".to_string(),
+        1 => "(1 match)".to_string(),
+        n => format!("({} matches)", HumanCount(n)),
+    }
+}
@@ -2,32 +2,712 @@ use axum::{
     routing::{get, post},
     Router,
     Json,
-    extract::State,
-    response::IntoResponse,
-    http::StatusCode,
+    extract::{rejection::JsonRejection, FromRequest, Path, Query, Request, State},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    http::{header, HeaderMap, StatusCode},
 };
+use base64::Engine;
+use clap::Parser;
+use futures::future::{self, BoxFuture, FutureExt, Shared, WeakShared};
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
-use std::sync::Arc;
+use solana_sdk::signature::{read_keypair_file, Keypair};
+use solana_sdk::signer::Signer;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex, RwLock,
+};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use std::future::Future;
 use tower_http::cors::CorsLayer;
-use crate::GrindArgs;
-use sha2::Digest;
-use rand::Rng;
+use tower_http::timeout::{RequestBodyTimeoutLayer, TimeoutError};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use vanity::attestation::attestation_message;
+use vanity::confusable::Blocklist;
+use vanity::estimate::{Watchdog, DEFAULT_WATCHDOG_MULTIPLE};
+use vanity::grind::{check_owner, derive_address, grind_parallel, grind_stream, Best, Found, GrindError, GrindEvent, Progress};
+use vanity::matcher::{ByteSum, Matcher, MatcherSpec, RuleKind, RuleSpec};
+use vanity::owners;
+use vanity::{
+    available_cpus, maybe_bs58_aware_lowercase, parse_owner, short_address, validated_last4,
+    GrindArgs,
+};
 
-#[derive(Clone)]
-struct AppState {
+use crate::cache::LruCache;
+use crate::metadata::Metadata;
+use crate::metrics::{Metrics, Reason};
+
+#[derive(Clone, Debug, Parser)]
+pub struct ServerArgs {
+    /// Address to listen on
+    #[clap(long, env = "VANITY_BIND", default_value = "0.0.0.0:3001")]
+    pub bind: SocketAddr,
+
+    /// Owner of generated addresses when a request doesn't specify one,
+    /// e.g. TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb or token-2022
+    #[clap(long, env = "VANITY_TOKEN_PROGRAM_ID", default_value_t = owners::TOKEN_PROGRAM.to_string())]
+    pub token_program_id: String,
+
+    /// Bearer token for the /admin endpoints, which don't exist without one
+    #[clap(long, env = "VANITY_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
+    /// JSON file overriding the reloadable options: token_program_id,
+    /// grind_timeout_secs, request_timeout_secs and max_queued_grinds, e.g.
+    /// {"token_program_id": "...", "max_queued_grinds": 8}. Re-read on
+    /// SIGHUP
+    #[clap(long, env = "VANITY_SERVER_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Seconds a grind may run before /generate gives up with a 503, or
+    /// /generate/stream with an error event; 0 for no limit. Reloadable
+    #[clap(long, default_value_t = 300)]
+    pub grind_timeout_secs: u64,
+
+    /// Warn, and flag /generate/stream progress as overdue, once a grind
+    /// exceeds this multiple of its expected attempts (0 disables)
+    #[clap(long, default_value_t = DEFAULT_WATCHDOG_MULTIPLE)]
+    pub watchdog_multiple: f64,
+
+    /// Seconds a client gets to send a request's headers
+    #[clap(long, default_value_t = 10)]
+    pub header_timeout_secs: u64,
+
+    /// Seconds a client gets to send a request's body
+    #[clap(long, default_value_t = 10)]
+    pub body_timeout_secs: u64,
+
+    /// Seconds to handle a request, except /generate and /generate/stream
+    /// which take as long as their grind; 0 for no limit. Reloadable
+    #[clap(long, default_value_t = 30)]
+    pub request_timeout_secs: u64,
+
+    /// Threads for each grind, unless a request asks for a number
+    #[clap(long, default_value_t = 1)]
+    pub grind_threads: u32,
+
+    /// Most threads a request can ask for; 0, or more than the number of
+    /// cores, uses the number of cores
+    #[clap(long, default_value_t = 0)]
+    pub max_grind_threads: u32,
+
+    /// Grinds that can run at once; unlimited if unset. Requests sharing
+    /// a grind (see /generate) share its slot
+    #[clap(long)]
+    pub max_concurrent_grinds: Option<usize>,
+
+    /// Requests that can wait for a grind slot before more are turned away
+    /// with a 503. Reloadable
+    #[clap(long, default_value_t = 32)]
+    pub max_queued_grinds: usize,
+
+    /// Retry-After seconds sent with those 503s
+    #[clap(long, default_value_t = 5)]
+    pub queue_full_retry_after_secs: u64,
+
+    /// Recent /generate results kept to answer identical requests with
+    /// instantly, which then get the same address and seed; 0 disables.
+    /// Requests can opt out with "fresh"
+    #[clap(long, default_value_t = 256)]
+    pub result_cache_size: usize,
+
+    /// Hosts a request's callback_url may point at, comma separated.
+    /// Without any, callbacks are refused
+    #[cfg(feature = "webhook")]
+    #[clap(long, env = "VANITY_WEBHOOK_HOSTS", value_delimiter = ',')]
+    pub webhook_hosts: Vec<String>,
+
+    /// Allow plain http callback urls, not just https
+    #[cfg(feature = "webhook")]
+    #[clap(long, default_value_t = false)]
+    pub webhook_allow_http: bool,
+
+    /// Look-alike patterns no generated address may contain, comma
+    /// separated, on top of any a request adds; see --avoid-lookalike on
+    /// grind
+    #[clap(long, env = "VANITY_AVOID_LOOKALIKES", value_delimiter = ',')]
+    pub avoid_lookalikes: Vec<String>,
+
+    /// Keypair file to sign results with, so clients can prove this server
+    /// generated them; responses then carry an attestation that
+    /// `vanity verify --signature` checks
+    #[clap(long, env = "VANITY_ATTESTATION_KEYPAIR")]
+    pub attestation_keypair: Option<PathBuf>,
+}
+
+impl ServerArgs {
+    /// The threads a grind gets when asking for `requested`: the default
+    /// if none, and always within [1, --max-grind-threads]
+    fn grind_threads(&self, requested: Option<u32>) -> u32 {
+        let max = match self.max_grind_threads {
+            0 => available_cpus(),
+            max => max.min(available_cpus()),
+        };
+        requested.unwrap_or(self.grind_threads).clamp(1, max)
+    }
+}
+
+/// Settings that can change while running, on SIGHUP. Requests take a
+/// snapshot when they start, so a reload never changes one midway.
+#[derive(Debug)]
+struct ServerConfig {
+    /// Owner for requests that don't specify one
     token_program_id: Pubkey,
+    grind_timeout_secs: u64,
+    request_timeout_secs: u64,
+    max_queued_grinds: usize,
+}
+
+/// The reloadable settings of a --config file; missing ones keep the
+/// value given on the command line
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    token_program_id: Option<String>,
+    grind_timeout_secs: Option<u64>,
+    request_timeout_secs: Option<u64>,
+    max_queued_grinds: Option<usize>,
+}
+
+impl ServerConfig {
+    /// Reads and validates the config for `args`
+    fn load(args: &ServerArgs) -> Result<ServerConfig, ServerError> {
+        let mut file = ConfigFile::default();
+        if let Some(path) = &args.config {
+            let invalid = |e: &dyn fmt::Display| ServerError::Config(format!("{}: {e}", path.display()));
+            let contents = std::fs::read_to_string(path).map_err(|e| invalid(&e))?;
+            file = serde_json::from_str(&contents).map_err(|e| invalid(&e))?;
+        }
+
+        let token_program_id = file
+            .token_program_id
+            .unwrap_or_else(|| args.token_program_id.clone());
+        let owner = parse_owner(&token_program_id)
+            .ok()
+            .filter(|owner| check_owner(owner).is_ok());
+        Ok(ServerConfig {
+            token_program_id: owner.ok_or(ServerError::InvalidTokenProgramId(token_program_id))?,
+            grind_timeout_secs: file.grind_timeout_secs.unwrap_or(args.grind_timeout_secs),
+            request_timeout_secs: file.request_timeout_secs.unwrap_or(args.request_timeout_secs),
+            max_queued_grinds: file.max_queued_grinds.unwrap_or(args.max_queued_grinds),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ServerError {
+    /// The configured token program id isn't a pubkey
+    InvalidTokenProgramId(String),
+    /// The --config file can't be read or parsed
+    Config(String),
+    /// The --attestation-keypair file can't be read
+    AttestationKeypair(String),
+    /// Binding or serving failed
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerError::InvalidTokenProgramId(id) => {
+                write!(f, "invalid token program id: {id}")
+            }
+            ServerError::Config(e) => write!(f, "invalid config: {e}"),
+            ServerError::AttestationKeypair(e) => write!(f, "invalid attestation keypair: {e}"),
+            ServerError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+impl From<std::io::Error> for ServerError {
+    fn from(e: std::io::Error) -> ServerError {
+        ServerError::Io(e)
+    }
+}
+
+struct AppState {
+    /// What the config is reloaded from
+    args: ServerArgs,
+    config: RwLock<Arc<ServerConfig>>,
+    /// Grinds currently running, so identical concurrent requests can
+    /// share one result instead of grinding twice. Held weakly, so a grind
+    /// nobody is waiting for any more is dropped, and so cancelled.
+    in_flight: Mutex<HashMap<GrindKey, WeakShared<GrindFuture>>>,
+    /// Recently finished grinds, answering identical requests without
+    /// grinding again
+    results: Mutex<LruCache<GrindKey, Found>>,
+    /// Number of /generate requests currently being handled
+    requests_in_flight: AtomicUsize,
+    /// Notified whenever requests_in_flight drops to zero
+    drained: Notify,
+    /// Set once shutdown starts; new /generate requests are refused
+    shutting_down: AtomicBool,
+    /// Notified by /admin/shutdown
+    shutdown_requested: Notify,
+    admin_token: Option<String>,
+    /// Signs results, if --attestation-keypair is set
+    attestation_keypair: Option<Keypair>,
+    metrics: Metrics,
+    /// Streaming grinds by job id, for /generate/{id}/cancel and
+    /// /generate/{id}/status
+    jobs: Mutex<HashMap<String, JobHandle>>,
+    /// Slots for running grinds, if limited by --max-concurrent-grinds
+    grind_slots: Option<Arc<Semaphore>>,
+    /// Requests waiting for a grind slot
+    queued: AtomicUsize,
+}
+
+/// A cancellable streaming grind, listed in `AppState::jobs` for as long
+/// as it is alive
+struct Job {
+    state: Arc<AppState>,
+    id: String,
+    cancel: CancellationToken,
+    progress: Arc<Mutex<JobProgress>>,
+}
+
+/// What `AppState::jobs` knows of a job
+#[derive(Clone)]
+struct JobHandle {
+    cancel: CancellationToken,
+    started: Instant,
+    progress: Arc<Mutex<JobProgress>>,
+}
+
+/// A job's attempts, rate and closest address, as its workers last
+/// reported them every `check_interval` attempts
+#[derive(Clone, Copy, Default)]
+struct JobProgress {
+    attempts: u64,
+    rate: f64,
+    best: Option<Best>,
+}
+
+impl Job {
+    fn start(state: Arc<AppState>) -> Result<Job, ApiError> {
+        Job::start_from(state, &mut OsRng)
+    }
+
+    /// Like [`Job::start`] but drawing the id from `entropy`, so a failing
+    /// source can be tested. A failure is a 500, as when a grind can't seed
+    fn start_from(state: Arc<AppState>, entropy: &mut impl RngCore) -> Result<Job, ApiError> {
+        // Random rather than sequential, since knowing an id is all it
+        // takes to cancel the job
+        let mut id = [0u8; 16];
+        if let Err(e) = entropy.try_fill_bytes(&mut id) {
+            let e = GrindError::Rng(e.to_string());
+            tracing::error!("Grind failed: {}", e);
+            return Err(ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Reason::GrindFailed,
+                grind_failure(&e),
+            ));
+        }
+        let id = to_hex(&id);
+        let handle = JobHandle {
+            cancel: CancellationToken::new(),
+            started: Instant::now(),
+            progress: Arc::default(),
+        };
+        state.jobs.lock().unwrap().insert(id.clone(), handle.clone());
+        Ok(Job {
+            state,
+            id,
+            cancel: handle.cancel,
+            progress: handle.progress,
+        })
+    }
 }
 
+impl Drop for Job {
+    fn drop(&mut self) {
+        // In case it's still waiting for a grind slot
+        self.cancel.cancel();
+        self.state.jobs.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Counts a request as waiting for a grind slot for as long as it is
+/// alive, so a client that gives up while queued leaves the queue
+struct QueuedGuard {
+    state: Arc<AppState>,
+}
+
+impl Drop for QueuedGuard {
+    fn drop(&mut self) {
+        self.state.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A request's place in line for a grind slot
+enum SlotTicket {
+    /// A free slot, or None if grinds are unlimited
+    Ready(Option<OwnedSemaphorePermit>),
+    /// Waiting for a slot, counted as queued until then
+    Queued(Arc<Semaphore>, QueuedGuard),
+}
+
+impl SlotTicket {
+    /// The slot once it's free, or None if grinds are unlimited
+    async fn slot(self) -> Option<OwnedSemaphorePermit> {
+        match self {
+            SlotTicket::Ready(slot) => slot,
+            // The semaphore is never closed
+            SlotTicket::Queued(slots, _queued) => Some(slots.acquire_owned().await.unwrap()),
+        }
+    }
+}
+
+/// Gets in line for a grind slot, taking one straight away if it's free,
+/// or fails fast with a 503 if the queue is full
+fn queue_for_slot(state: &Arc<AppState>) -> Result<SlotTicket, ApiError> {
+    let Some(slots) = &state.grind_slots else {
+        return Ok(SlotTicket::Ready(None));
+    };
+    if let Ok(permit) = slots.clone().try_acquire_owned() {
+        return Ok(SlotTicket::Ready(Some(permit)));
+    }
+
+    if state.queued.fetch_add(1, Ordering::Relaxed) >= state.config().max_queued_grinds {
+        state.queued.fetch_sub(1, Ordering::Relaxed);
+        tracing::warn!("Grind queue is full, turning a request away");
+        return Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            Reason::QueueFull,
+            "Too many requests are waiting, try again later",
+        )
+        .retry_after(state.args.queue_full_retry_after_secs));
+    }
+    let queued = QueuedGuard { state: state.clone() };
+    Ok(SlotTicket::Queued(slots.clone(), queued))
+}
+
+/// Takes a grind slot, waiting in the queue if none is free, or fails
+/// fast with a 503 if the queue is full too. None if grinds are unlimited
+async fn grind_slot(state: &Arc<AppState>) -> Result<Option<OwnedSemaphorePermit>, ApiError> {
+    Ok(queue_for_slot(state)?.slot().await)
+}
+
+/// Counts a request as in flight for as long as it is alive, so that every
+/// exit path (early return, error, panic) gives the slot back
+struct InFlightGuard {
+    state: Arc<AppState>,
+}
+
+impl InFlightGuard {
+    fn new(state: Arc<AppState>) -> InFlightGuard {
+        let in_flight = state.requests_in_flight.fetch_add(1, Ordering::AcqRel) + 1;
+        tracing::debug!("{} requests in flight", in_flight);
+        InFlightGuard { state }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.state.requests_in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.state.drained.notify_waiters();
+        }
+    }
+}
+
+/// A grind's outcome as seen by every request waiting on it
+type GrindFuture = BoxFuture<'static, Result<Found, GrindError>>;
+type SharedGrind = Shared<GrindFuture>;
+
+/// Everything that determines what a grind is allowed to return. Two
+/// requests with equal keys are interchangeable, so anything that should
+/// make requests distinct (e.g. asking for a fresh seed) must live here.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct GrindKey {
+    base: Pubkey,
+    owner: Pubkey,
+    matcher: MatcherSpec,
+    seed_prefix: Option<String>,
+    seed_suffix: Option<String>,
+    require_off_curve: bool,
+}
+
+impl GrindKey {
+    fn new(args: &GrindArgs) -> GrindKey {
+        GrindKey {
+            base: args.base,
+            owner: args.owner,
+            matcher: normalized(args.matcher_spec()),
+            seed_prefix: args.seed_prefix.clone(),
+            seed_suffix: args.seed_suffix.clone(),
+            require_off_curve: args.require_off_curve,
+        }
+    }
+}
+
+/// `spec` in a canonical form, so specs that only differ in what the
+/// matcher ignores (the case of case-insensitive targets, the order of
+/// any-of prefixes) make the same key
+fn normalized(mut spec: MatcherSpec) -> MatcherSpec {
+    for rule in &mut spec.rules {
+        let case_insensitive = rule.case_insensitive;
+        let fold = |target: &mut String| {
+            *target = maybe_bs58_aware_lowercase(target, case_insensitive);
+        };
+        match &mut rule.kind {
+            RuleKind::Prefix(prefixes) => {
+                prefixes.iter_mut().for_each(fold);
+                prefixes.sort();
+                prefixes.dedup();
+            }
+            RuleKind::Suffix(target) | RuleKind::Contains(target) | RuleKind::Pattern(target) => {
+                fold(target)
+            }
+            _ => {}
+        }
+    }
+    spec.avoid_lookalikes.sort();
+    spec.avoid_lookalikes.dedup();
+    spec
+}
+
+/// Suffix ground when a request doesn't ask for anything
+const DEFAULT_SUFFIX: &str = "Loop";
+
 #[derive(Deserialize)]
 struct GenerateRequest {
     base: String,
+    /// A pubkey or a program name like "token-2022", see vanity::owners.
+    /// Defaults to the server's token program id
+    owner: Option<String>,
+    /// Simple target; both must match
+    prefix: Option<String>,
+    suffix: Option<String>,
+    /// A suffix of exactly 4 base58 chars, instead of suffix
+    last4: Option<String>,
+    #[serde(default)]
+    case_insensitive: bool,
+    /// Leading zero bytes (leading 1s) required on top of prefix/suffix
+    leading_zeros: Option<usize>,
+    /// Byte sum remainder required on top of prefix/suffix, e.g.
+    /// {"modulus": 16, "remainder": 3}
+    byte_sum: Option<ByteSum>,
+    /// Chars required at fixed positions on top of prefix/suffix, with ?
+    /// for any char, e.g. "So?Lab"
+    pattern: Option<String>,
+    /// Full matcher, instead of prefix/suffix/last4/case_insensitive/leading_zeros/byte_sum/pattern
+    matcher: Option<MatcherSpec>,
+    /// Look-alikes to avoid, with either kind of target and on top of the
+    /// server's own, e.g. ["PayPal"]
+    #[serde(default)]
+    avoid_lookalikes: Vec<String>,
+    /// Fixed text around the random part of the seed
+    seed_prefix: Option<String>,
+    seed_suffix: Option<String>,
+    /// Only return addresses off the ed25519 curve
+    #[serde(default)]
+    require_off_curve: bool,
+    /// Threads to grind with, clamped to the server's limit
+    threads: Option<u32>,
+    /// Grind a new address even if an identical request was just answered
+    /// or is still being ground, and don't hand it to anyone else.
+    /// Without it /generate may return an address and seed that other
+    /// callers got too
+    #[serde(default)]
+    fresh: bool,
+    /// Answer /generate with a 202 at once and POST the result here when
+    /// found. Needs the webhook feature and an allowed host
+    callback_url: Option<String>,
+    /// Include the closest address so far in /generate/stream progress
+    /// events, at some cost to speed
+    #[serde(default)]
+    best_so_far: bool,
+    #[serde(flatten)]
+    response: ResponseOptions,
+}
+
+/// What a response includes beyond the address and seed, which is all it
+/// has by default
+#[derive(Deserialize, Clone, Default)]
+struct ResponseOptions {
+    /// Extra encodings of the address
+    #[serde(default)]
+    encodings: Vec<Encoding>,
+    /// Attempts and time taken
+    #[serde(default)]
+    details: bool,
+    /// Only the address and seed, whatever else is asked for
+    #[serde(default)]
+    minimal: bool,
+    /// The grind as the server resolved it, defaults and all
+    #[serde(default)]
+    echo_config: bool,
+    /// When, where and by which version the result was generated (see
+    /// Metadata), for self-describing pools. Also added to callbacks
+    #[serde(default)]
+    metadata: bool,
+    /// On an exact-case grind, the expected attempts of its
+    /// case-insensitive variant too, to weigh relaxing case against
+    #[serde(default)]
+    compare_case: bool,
+    /// A shortened address for display like Vnty…Loop: {} for the first
+    /// and last 4 chars, or e.g. {"head": 3, "tail": 4}
+    display: Option<DisplayOptions>,
+    /// Which rules, and of their prefixes which one, the address satisfied
+    #[serde(default)]
+    matched_rules: bool,
+}
+
+impl ResponseOptions {
+    /// The matcher to report matched_rules with, if they're asked for,
+    /// so a request builds it just the once
+    fn rule_reporter(&self, args: &GrindArgs) -> Option<Matcher> {
+        (self.matched_rules && !self.minimal)
+            .then(|| args.matcher().ok())
+            .flatten()
+    }
+}
+
+/// How much of the address a shortened display form keeps
+#[derive(Deserialize, Clone, Copy)]
+struct DisplayOptions {
+    #[serde(default = "DisplayOptions::default_len")]
+    head: usize,
+    #[serde(default = "DisplayOptions::default_len")]
+    tail: usize,
+}
+
+impl DisplayOptions {
+    fn default_len() -> usize {
+        4
+    }
+}
+
+/// What a grind was actually asked for after defaults and validation,
+/// for responses to echo back
+#[derive(Serialize, Clone)]
+struct GrindConfig {
+    base: String,
+    owner: String,
+    matcher: MatcherSpec,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed_suffix: Option<String>,
+    require_off_curve: bool,
+    threads: u32,
+}
+
+impl GrindConfig {
+    fn new(args: &GrindArgs) -> GrindConfig {
+        GrindConfig {
+            base: args.base.to_string(),
+            owner: args.owner.to_string(),
+            matcher: args.matcher_spec(),
+            seed_prefix: args.seed_prefix.clone(),
+            seed_suffix: args.seed_suffix.clone(),
+            require_off_curve: args.require_off_curve,
+            threads: args.num_cpus.max(1),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Encoding {
+    Base58,
+    Hex,
+    Base64,
 }
 
 #[derive(Serialize)]
 struct GenerateResponse {
     address: String,
     seed: String,
+    /// The address as case-insensitive rules compared it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matched_lower: Option<String>,
+    /// Which rules (and of their prefixes, which one) the address
+    /// satisfied, see Matcher::matched_rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matched_rules: Option<Vec<RuleSpec>>,
+    /// The address shortened for display, if asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address_hex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address_base64: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attempts: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config: Option<GrindConfig>,
+    /// Answered from the result cache rather than ground for this request
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    cached: bool,
+    /// The server's signature over the result, if it has a keypair
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attestation: Option<Attestation>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    metadata: Option<Metadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    case_comparison: Option<CaseComparison>,
+}
+
+/// What an exact-case grind cost against what ignoring case would have
+#[derive(Serialize)]
+struct CaseComparison {
+    /// Attempts the exact-case grind took, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attempts: Option<u64>,
+    expected_attempts: u64,
+    case_insensitive_expected_attempts: u64,
+    /// How many times fewer attempts ignoring case is expected to take
+    speedup: f64,
+}
+
+impl CaseComparison {
+    /// None for targets that ignore case throughout already or can't be
+    /// estimated
+    fn new(spec: &MatcherSpec, attempts: Option<u64>) -> Option<CaseComparison> {
+        if spec.rules.iter().all(|rule| rule.case_insensitive) {
+            return None;
+        }
+        let expected = Matcher::new(spec, 0).ok()?.expected_attempts()?;
+        let insensitive = Matcher::new(&spec.ignoring_case(), 0).ok()?.expected_attempts()?;
+        Some(CaseComparison {
+            attempts,
+            expected_attempts: expected.round() as u64,
+            case_insensitive_expected_attempts: insensitive.round() as u64,
+            speedup: expected / insensitive,
+        })
+    }
+}
+
+/// A signature by `server_pubkey` over the
+/// [`attestation_message`](vanity::attestation::attestation_message) for
+/// a result and `timestamp`
+#[derive(Serialize)]
+struct Attestation {
+    timestamp: u64,
+    signature: String,
+    server_pubkey: String,
 }
 
 #[derive(Serialize)]
@@ -35,109 +715,1020 @@ struct ErrorResponse {
     error: String,
 }
 
-async fn health_check() -> impl IntoResponse {
+/// An ErrorResponse with the status it should be sent with, and why, for
+/// /metrics
+struct ApiError {
+    status: StatusCode,
+    reason: Reason,
+    error: String,
+    /// Seconds for a Retry-After header
+    retry_after: Option<u64>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, reason: Reason, error: impl Into<String>) -> ApiError {
+        ApiError {
+            status,
+            reason,
+            error: error.into(),
+            retry_after: None,
+        }
+    }
+
+    fn retry_after(self, secs: u64) -> ApiError {
+        ApiError {
+            retry_after: Some(secs),
+            ..self
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        // Picked up by count_rejections, which can see the state
+        let mut response = (self.status, Json(ErrorResponse { error: self.error })).into_response();
+        response.extensions_mut().insert(self.reason);
+        if let Some(secs) = self.retry_after {
+            response.headers_mut().insert(header::RETRY_AFTER, secs.into());
+        }
+        response
+    }
+}
+
+/// Counts every error response by the reason it was tagged with
+async fn count_rejections(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if let Some(&reason) = response.extensions().get::<Reason>() {
+        state.metrics.rejected(reason);
+    }
+    response
+}
+
+/// Fails a request that runs longer than the configured request timeout
+/// with a 408, as of when it started. A timeout of 0 never expires.
+async fn request_timeout(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let timeout = match state.config().request_timeout_secs {
+        0 => return next.run(request).await,
+        secs => Duration::from_secs(secs),
+    };
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("Request timed out after {:?}", timeout);
+            ApiError::new(StatusCode::REQUEST_TIMEOUT, Reason::Timeout, "Request timed out")
+                .into_response()
+        }
+    }
+}
+
+/// Like axum's Json extractor, but bad bodies are reported as an
+/// ErrorResponse like every other error
+struct ApiJson<T>(T);
+
+#[axum::async_trait]
+impl<S, T> FromRequest<S> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(rejection) => {
+                // Syntax and data errors both mean the body didn't fit the
+                // schema; the message names the offending field
+                let (status, reason) = match rejection {
+                    JsonRejection::JsonDataError(_) | JsonRejection::JsonSyntaxError(_) => {
+                        (StatusCode::UNPROCESSABLE_ENTITY, Reason::InvalidBody)
+                    }
+                    _ if is_body_timeout(&rejection) => (StatusCode::REQUEST_TIMEOUT, Reason::Timeout),
+                    _ => (rejection.status(), Reason::InvalidBody),
+                };
+                tracing::debug!("Rejected request body: {}", rejection.body_text());
+                Err(ApiError::new(status, reason, rejection.body_text()))
+            }
+        }
+    }
+}
+
+/// Whether the body couldn't be read because of the body timeout
+fn is_body_timeout(rejection: &JsonRejection) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(rejection);
+    while let Some(e) = source {
+        if e.is::<TimeoutError>() {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+#[derive(Deserialize)]
+struct HealthQuery {
+    /// "1" or "true" to prove the grinder works, see deep_health
+    deep: Option<String>,
+}
+
+/// Base the deep health check grinds under, as the server has no default
+const HEALTH_BASE: Pubkey = Pubkey::new_from_array([0; 32]);
+
+/// Suffix the deep health check grinds for, ~58 attempts
+const HEALTH_SUFFIX: &str = "a";
+
+/// How long the deep health check's grind may take, which even a busy
+/// server should manage in a fraction of
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn health_check(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HealthQuery>,
+) -> impl IntoResponse {
     tracing::info!("Health check request received");
     tracing::debug!("Processing health check request");
+    let deep = matches!(query.deep.as_deref(), Some("1" | "true"));
+    let health = if deep { deep_health(&state).await } else { Ok(()) };
+    let (status, body) = match health {
+        Ok(()) => (StatusCode::OK, "ok".to_string()),
+        Err(e) => {
+            tracing::error!("Deep health check failed: {}", e);
+            (StatusCode::SERVICE_UNAVAILABLE, format!("unhealthy: {e}"))
+        }
+    };
     (
-        StatusCode::OK,
+        status,
         [
             ("content-type", "text/plain"),
             ("connection", "close"),
         ],
-        "ok"
+        body
     )
 }
 
-fn grind_with_result(args: GrindArgs) -> (String, Pubkey) {
-    tracing::info!("Starting vanity address generation");
-    let mut seed = [0u8; 16];
-    let mut found = false;
-    let mut address = Pubkey::default();
+/// Grinds a 1-char suffix under the configured owner, outside the grind
+/// slots and within [`HEALTH_TIMEOUT`], and checks the match really
+/// derives from its seed
+async fn deep_health(state: &AppState) -> Result<(), String> {
+    let owner = state.config().token_program_id;
+    let args = GrindArgs {
+        matcher: Some(MatcherSpec::from_target(None, Some(HEALTH_SUFFIX), false)),
+        num_cpus: 1,
+        ..GrindArgs::new(HEALTH_BASE, owner)
+    };
+    let deadline = Instant::now() + HEALTH_TIMEOUT;
+    let grind = tokio::task::spawn_blocking(move || {
+        grind_parallel(&args, |_| match Instant::now() < deadline {
+            true => ControlFlow::Continue(()),
+            false => ControlFlow::Break(()),
+        })
+    });
 
-    // Run the grind function with a closure to capture the result
-    let base_sha = sha2::Sha256::new().chain_update(args.base);
-    let prefix = args.prefix.as_deref().unwrap_or("");
-    let suffix = args.suffix.as_deref().unwrap_or("");
-    
-    let timer = std::time::Instant::now();
-    let mut count = 0_u64;
+    let found = match tokio::time::timeout(HEALTH_TIMEOUT, grind).await {
+        Ok(Ok(Ok(found))) => found,
+        Ok(Ok(Err(GrindError::Cancelled))) | Err(_) => {
+            return Err(format!("no match within {HEALTH_TIMEOUT:?}"))
+        }
+        Ok(Ok(Err(e))) => return Err(e.to_string()),
+        Ok(Err(e)) => return Err(format!("grind worker failed: {e}")),
+    };
+    let derived = derive_address(&found.base, found.seed.as_bytes(), &owner, false)
+        .map_err(|e| format!("seed {} doesn't derive: {e}", found.seed))?;
+    if derived != found.address {
+        return Err(format!(
+            "seed {} derives {derived}, not the match {}",
+            found.seed, found.address
+        ));
+    }
+    if !derived.to_string().ends_with(HEALTH_SUFFIX) {
+        return Err(format!("match {derived} doesn't end with {HEALTH_SUFFIX}"));
+    }
+    Ok(())
+}
 
-    while !found {
-        let mut seed_iter = rand::thread_rng().sample_iter(&rand::distributions::Alphanumeric).take(16);
-        seed = std::array::from_fn(|_| seed_iter.next().unwrap());
+async fn metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let in_flight = state.requests_in_flight.load(Ordering::Relaxed);
+    let queued = state.queued.load(Ordering::Relaxed);
+    (
+        [("content-type", "text/plain; version=0.0.4")],
+        state.metrics.render(in_flight, queued),
+    )
+}
 
-        let pubkey_bytes: [u8; 32] = base_sha
-            .clone()
-            .chain_update(seed)
-            .chain_update(args.owner)
-            .finalize()
-            .into();
-        let pubkey = fd_bs58::encode_32(pubkey_bytes);
-        let out_str_target_check = if args.case_insensitive {
-            pubkey.to_ascii_lowercase()
-        } else {
-            pubkey.clone()
-        };
+fn grind_with_result(args: GrindArgs, cancel: &CancellationToken) -> Result<Found, GrindError> {
+    tracing::info!("Starting vanity address generation");
 
-        count += 1;
+    let expected = args.matcher()?.expected_attempts().unwrap_or(f64::INFINITY);
+    tracing::debug!("Expecting ~{:.0} attempts", expected);
+    let watchdog = Watchdog::new(expected, args.watchdog_multiple, 1);
 
-        if out_str_target_check.starts_with(prefix) && out_str_target_check.ends_with(suffix) {
-            address = Pubkey::new_from_array(pubkey_bytes);
-            found = true;
+    let mut checked = 0;
+    let found = grind_parallel(&args, |&Progress { attempts, .. }| {
+        if cancel.is_cancelled() {
+            return ControlFlow::Break(());
         }
-    }
+        if watchdog.crossed(checked, attempts) {
+            tracing::warn!(
+                "Grind exceeded {}x its expected {:.0} attempts without a match",
+                args.watchdog_multiple,
+                expected
+            );
+        }
+        checked = attempts;
+        ControlFlow::Continue(())
+    })?;
 
-    let duration = timer.elapsed();
     tracing::info!(
         "Vanity address generated in {:?} after {} attempts",
-        duration,
-        count
+        found.elapsed,
+        found.attempts
     );
-    
-    (std::str::from_utf8(&seed).unwrap().to_string(), address)
+    Ok(found)
+}
+
+#[cfg(feature = "webhook")]
+#[derive(Serialize)]
+struct AcceptedResponse {
+    status: &'static str,
 }
 
 async fn generate_vanity_address(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<GenerateRequest>,
-) -> Result<Json<GenerateResponse>, Json<ErrorResponse>> {
+    ApiJson(req): ApiJson<GenerateRequest>,
+) -> Result<Response, ApiError> {
     tracing::info!("Received vanity address generation request");
+    let in_flight = InFlightGuard::new(state.clone());
+    let args = grind_args(&state, &req)?;
+    #[cfg(feature = "webhook")]
+    let callback = callback_url(&state, &req)?;
+    #[cfg(not(feature = "webhook"))]
+    callback_url(&state, &req)?;
+    let fresh = req.fresh;
+    let cached = cached_result(&state, &args, fresh);
+
+    #[cfg(feature = "webhook")]
+    if let Some(url) = callback {
+        let metadata = req.response.metadata && !req.response.minimal;
+        // Still in flight, so shutdown waits for the grind and delivery.
+        // Any wait for a grind slot happens in here too, after the 202.
+        tokio::spawn(async move {
+            let _in_flight = in_flight;
+            let found = match cached {
+                Some(found) => Ok(found),
+                None => grind_found(&state, args, fresh).await,
+            };
+            match found {
+                Ok(found) => {
+                    let payload = crate::webhook::WebhookPayload {
+                        address: found.address.to_string(),
+                        seed: found.seed,
+                        attempts: found.attempts,
+                        metadata: metadata.then(Metadata::now),
+                    };
+                    crate::webhook::deliver(url, payload).await;
+                }
+                // Nobody gets this response, so count it here
+                Err(e) => state.metrics.rejected(e.reason),
+            }
+        });
+        let accepted = AcceptedResponse { status: "accepted" };
+        return Ok((StatusCode::ACCEPTED, Json(accepted)).into_response());
+    }
+
+    let config = GrindConfig::new(&args);
+    let reporter = req.response.rule_reporter(&args);
+    let (base, owner) = (args.base, args.owner);
+    let was_cached = cached.is_some();
+    let Found {
+        seed,
+        address,
+        attempts,
+        elapsed,
+        ..
+    } = match cached {
+        Some(found) => found,
+        None => grind_found(&state, args, fresh).await?,
+    };
+    drop(in_flight);
+
+    let attestation = if req.response.minimal {
+        None
+    } else {
+        attest(&state, &address, &seed, &base, &owner)
+    };
+    let mut response = generate_response(
+        &req.response,
+        seed,
+        address,
+        &config,
+        Some((attempts, elapsed)),
+        reporter.as_ref(),
+    );
+    response.cached = was_cached && !req.response.minimal;
+    response.attestation = attestation;
+    Ok(Json(response).into_response())
+}
+
+/// A recent result for the same request as `args`, unless it asked for a
+/// fresh one
+fn cached_result(state: &AppState, args: &GrindArgs, fresh: bool) -> Option<Found> {
+    if fresh || state.args.result_cache_size == 0 {
+        return None;
+    }
+    let found = state.results.lock().unwrap().get(&GrindKey::new(args));
+    match &found {
+        Some(found) => {
+            tracing::info!("Answering from the result cache: {}", found.address);
+            state.metrics.cache_hit();
+        }
+        None => state.metrics.cache_miss(),
+    }
+    found
+}
+
+/// Runs the grind for /generate, or joins an identical one already running
+/// unless `fresh`
+async fn grind_found(
+    state: &Arc<AppState>,
+    args: GrindArgs,
+    fresh: bool,
+) -> Result<Found, ApiError> {
+    tracing::info!("Starting vanity address generation");
+    match shared_grind(state, args, fresh).await? {
+        Ok(found) => {
+            tracing::info!("Successfully generated vanity address: {}", found.address);
+            tracing::debug!("Generation completed with seed: {}", found.seed);
+            state.metrics.generated();
+            Ok(found)
+        }
+        Err(e @ GrindError::TimedOut(_)) => {
+            tracing::warn!("Grind failed: {}", e);
+            Err(ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                Reason::GrindTimeout,
+                grind_failure(&e),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Grind failed: {}", e);
+            Err(ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Reason::GrindFailed,
+                grind_failure(&e),
+            ))
+        }
+    }
+}
+
+/// What a client is told about a failed grind: only that it failed,
+/// unless it's something the operator has to fix
+fn grind_failure(e: &GrindError) -> String {
+    match e {
+        GrindError::Rng(_) => format!("Failed to generate vanity address: {e}"),
+        GrindError::TimedOut(budget) => format!("No match found within {budget:?}"),
+        _ => "Failed to generate vanity address".to_string(),
+    }
+}
+
+/// The request's validated callback_url, if it has one
+#[cfg(feature = "webhook")]
+fn callback_url(
+    state: &AppState,
+    req: &GenerateRequest,
+) -> Result<Option<reqwest::Url>, ApiError> {
+    let Some(url) = req.callback_url.as_deref() else {
+        return Ok(None);
+    };
+    let args = &state.args;
+    crate::webhook::validate(url, &args.webhook_hosts, args.webhook_allow_http)
+        .map(Some)
+        .map_err(|e| {
+            tracing::warn!("Rejected callback_url {}: {}", url, e);
+            ApiError::new(StatusCode::BAD_REQUEST, Reason::InvalidCallback, e)
+        })
+}
+
+/// Refuses a callback_url, which needs the webhook feature
+#[cfg(not(feature = "webhook"))]
+fn callback_url(_state: &AppState, req: &GenerateRequest) -> Result<(), ApiError> {
+    match req.callback_url {
+        Some(_) => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            Reason::InvalidCallback,
+            "callback_url is not supported by this server",
+        )),
+        None => Ok(()),
+    }
+}
+
+#[derive(Serialize)]
+struct JobEvent<'a> {
+    job_id: &'a str,
+}
+
+#[derive(Serialize)]
+struct ProgressEvent {
+    attempts: u64,
+    rate: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    best: Option<BestEvent>,
+    /// Past the watchdog multiple of the expected attempts, so the target
+    /// may be infeasible
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    overdue: bool,
+}
+
+/// The closest address so far, matching `depth` of `target_depth` chars
+#[derive(Serialize)]
+struct BestEvent {
+    depth: usize,
+    target_depth: usize,
+    address: String,
+}
+
+impl From<Best> for BestEvent {
+    fn from(best: Best) -> BestEvent {
+        BestEvent {
+            depth: best.depth,
+            target_depth: best.target_depth,
+            address: best.address.to_string(),
+        }
+    }
+}
+
+/// Like /generate, but streams a `started` event with the job id, then
+/// `progress` events while grinding, and ends with a `found` or `error`
+/// event, or `cancelled` if the job is cancelled. Closing the stream
+/// stops the grind too.
+async fn generate_vanity_address_stream(
+    State(state): State<Arc<AppState>>,
+    ApiJson(req): ApiJson<GenerateRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ApiError> {
+    tracing::info!("Received streaming vanity address generation request");
+    // Held by the stream, so the request counts as in flight until it ends
+    let in_flight = InFlightGuard::new(state.clone());
+    let args = GrindArgs {
+        track_best: req.best_so_far,
+        ..grind_args(&state, &req)?
+    };
+    // Queued inside the stream, so the client has its job id to cancel
+    // with while it waits
+    let ticket = queue_for_slot(&state)?;
+
+    let job = Job::start(state.clone())?;
+    tracing::info!("Starting streaming vanity address generation, job {}", job.id);
+    let started = Event::default()
+        .event("started")
+        .json_data(JobEvent { job_id: &job.id });
+
+    let options = req.response;
+    let config = GrindConfig::new(&args);
+    let reporter = options.rule_reporter(&args);
+    let (base, owner) = (args.base, args.owner);
+    // grind_args validated the matcher; targets that can't be estimated
+    // are never overdue, nor is anything with the watchdog off
+    let expected = args
+        .matcher()
+        .ok()
+        .and_then(|matcher| matcher.expected_attempts())
+        .filter(|_| args.watchdog_multiple > 0.0);
+    let overdue_after = expected.unwrap_or(f64::INFINITY) * args.watchdog_multiple;
+    let progress = job.progress.clone();
+    let cancel = job.cancel.clone();
+    // Spawned rather than left to the stream, so the grind, and the job's
+    // status, keep going however slowly the client reads
+    let grind = tokio::spawn(async move {
+        let slot = tokio::select! {
+            slot = ticket.slot() => slot,
+            // Cancelled, or its client went away, while queued
+            () = cancel.cancelled() => return None,
+        };
+        // Straight from the workers rather than the progress events
+        let watch = move |&Progress { attempts, elapsed, best }: &Progress| {
+            // The grind holds its slot until it's over
+            let _slot = &slot;
+            let rate = attempts as f64 / elapsed.as_secs_f64();
+            *progress.lock().unwrap() = JobProgress { attempts, rate, best };
+        };
+        Some(grind_stream(args, watch))
+    });
+    let grind_events = futures::stream::once(grind)
+        .filter_map(|joined| future::ready(joined.ok().flatten()))
+        .flatten()
+        .map(move |event| match event {
+            GrindEvent::Progress { attempts, rate, best } => {
+                Event::default().event("progress").json_data(ProgressEvent {
+                    attempts,
+                    rate,
+                    best: best.map(BestEvent::from),
+                    overdue: attempts as f64 > overdue_after,
+                })
+            }
+            GrindEvent::Found { seed, address } => {
+                tracing::info!("Successfully generated vanity address: {}", address);
+                in_flight.state.metrics.generated();
+                let attestation = if options.minimal {
+                    None
+                } else {
+                    attest(&in_flight.state, &address, &seed, &base, &owner)
+                };
+                // Progress events carry the attempts, so no details
+                let mut response =
+                    generate_response(&options, seed, address, &config, None, reporter.as_ref());
+                response.attestation = attestation;
+                Event::default().event("found").json_data(response)
+            }
+            GrindEvent::Error(e) => {
+                tracing::error!("Grind failed: {}", e);
+                let reason = match e {
+                    GrindError::TimedOut(_) => Reason::GrindTimeout,
+                    _ => Reason::GrindFailed,
+                };
+                in_flight.state.metrics.rejected(reason);
+                Event::default().event("error").json_data(ErrorResponse {
+                    error: grind_failure(&e),
+                })
+            }
+        });
+
+    // The job stays registered until the stream is done with
+    let cancelled = job.cancel.clone().cancelled_owned();
+    let finished = futures::stream::once(async move {
+        job.cancel.is_cancelled().then(|| {
+            tracing::info!("Job {} was cancelled", job.id);
+            Event::default().event("cancelled").json_data(JobEvent { job_id: &job.id })
+        })
+    });
+    let events = futures::stream::once(future::ready(started))
+        .chain(grind_events.take_until(cancelled))
+        .chain(finished.filter_map(future::ready));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Serialize)]
+struct CancelResponse {
+    cancelled: String,
+}
+
+/// Stops the streaming grind with this job id
+async fn cancel_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<CancelResponse>, ApiError> {
+    match state.jobs.lock().unwrap().get(&id) {
+        Some(job) => {
+            tracing::info!("Cancelling job {}", id);
+            job.cancel.cancel();
+            Ok(Json(CancelResponse { cancelled: id }))
+        }
+        None => Err(ApiError::new(StatusCode::NOT_FOUND, Reason::UnknownJob, "Unknown job")),
+    }
+}
+
+#[derive(Serialize)]
+struct JobStatus {
+    job_id: String,
+    elapsed_ms: u64,
+    attempts: u64,
+    rate: f64,
+    /// Only tracked if the job was started with best_so_far
+    #[serde(skip_serializing_if = "Option::is_none")]
+    best: Option<BestEvent>,
+}
+
+/// How the streaming grind with this job id is going, for clients that
+/// would rather poll than read progress events. Finished jobs are gone.
+async fn job_status(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<JobStatus>, ApiError> {
+    let Some(job) = state.jobs.lock().unwrap().get(&id).cloned() else {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, Reason::UnknownJob, "Unknown job"));
+    };
+    let progress = *job.progress.lock().unwrap();
+    Ok(Json(JobStatus {
+        job_id: id,
+        elapsed_ms: job.started.elapsed().as_millis() as u64,
+        attempts: progress.attempts,
+        rate: progress.rate,
+        best: progress.best.map(BestEvent::from),
+    }))
+}
+
+/// Validates a request into what to grind, defaulting to the Loop suffix
+fn grind_args(state: &AppState, req: &GenerateRequest) -> Result<GrindArgs, ApiError> {
+    if state.shutting_down.load(Ordering::Acquire) {
+        return Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            Reason::ShuttingDown,
+            "Server is shutting down",
+        ));
+    }
     tracing::debug!("Request details - base address: {}", req.base);
-    
+
     // Validate base address
-    if let Err(_) = Pubkey::try_from(req.base.as_str()) {
+    let base = Pubkey::try_from(req.base.as_str()).map_err(|_| {
         tracing::error!("Invalid base address provided: {}", req.base);
-        return Err(Json(ErrorResponse {
-            error: "Invalid base address".to_string(),
-        }));
-    }
+        ApiError::new(StatusCode::BAD_REQUEST, Reason::InvalidBase, "Invalid base address")
+    })?;
     tracing::debug!("Base address validation successful");
 
+    let config = state.config();
+    let owner = match req.owner.as_deref() {
+        Some(owner) => parse_owner(owner).map_err(|_| {
+            tracing::error!("Invalid owner provided: {}", owner);
+            ApiError::new(StatusCode::BAD_REQUEST, Reason::InvalidOwner, "Invalid owner")
+        })?,
+        None => config.token_program_id,
+    };
+    check_owner(&owner).map_err(|e| {
+        tracing::error!("Unusable owner provided: {}", owner);
+        ApiError::new(StatusCode::BAD_REQUEST, Reason::InvalidOwner, e.to_string())
+    })?;
+
+    if req.suffix.is_some() && req.last4.is_some() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            Reason::ConflictingTarget,
+            "Use either suffix or last4, not both",
+        ));
+    }
+    let last4 = req.last4.as_deref().map(validated_last4).transpose().map_err(|e| {
+        tracing::error!("Invalid last4 provided: {}", e);
+        ApiError::new(StatusCode::BAD_REQUEST, Reason::InvalidLast4, e.to_string())
+    })?;
+    let suffix = last4.or(req.suffix.as_deref());
+
+    let simple = req.prefix.is_some()
+        || suffix.is_some()
+        || req.case_insensitive
+        || req.leading_zeros.is_some()
+        || req.byte_sum.is_some()
+        || req.pattern.is_some();
+    let mut matcher = match &req.matcher {
+        Some(_) if simple => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                Reason::ConflictingTarget,
+                "Use either matcher or prefix/suffix/last4/case_insensitive/leading_zeros/byte_sum/pattern, not both",
+            ));
+        }
+        Some(matcher) => matcher.clone(),
+        None if req.prefix.is_none()
+            && suffix.is_none()
+            && req.leading_zeros.is_none()
+            && req.byte_sum.is_none()
+            && req.pattern.is_none() =>
+        {
+            MatcherSpec::from_target(None, Some(DEFAULT_SUFFIX), req.case_insensitive)
+        }
+        None => {
+            let mut matcher = MatcherSpec::from_target(
+                req.prefix.as_deref(),
+                suffix,
+                req.case_insensitive,
+            );
+            matcher.add_pattern(req.pattern.as_deref(), req.case_insensitive);
+            matcher.add_leading_zeros(req.leading_zeros);
+            matcher.add_byte_sum(req.byte_sum);
+            matcher
+        }
+    };
+    matcher.avoid_lookalikes.extend(state.args.avoid_lookalikes.iter().cloned());
+    matcher.avoid_lookalikes.extend(req.avoid_lookalikes.iter().cloned());
+
     // Create GrindArgs for the vanity generator
     let args = GrindArgs {
-        base: Pubkey::try_from(req.base.as_str()).unwrap(),
-        owner: state.token_program_id,
-        prefix: None,
-        suffix: Some("Loop".to_string()),
-        case_insensitive: false,
-        logfile: None,
-        num_cpus: 0,
+        matcher: Some(matcher),
+        seed_prefix: req.seed_prefix.clone(),
+        seed_suffix: req.seed_suffix.clone(),
+        require_off_curve: req.require_off_curve,
+        num_cpus: state.args.grind_threads(req.threads),
+        max_secs: (config.grind_timeout_secs > 0).then_some(config.grind_timeout_secs as f64),
+        watchdog_multiple: state.args.watchdog_multiple,
+        ..GrindArgs::new(base, owner)
     };
-    tracing::debug!("GrindArgs configured with suffix: Loop");
-
-    // Run the grind function
-    tracing::info!("Starting vanity address generation");
-    let (seed, address) = grind_with_result(args);
-    tracing::info!("Successfully generated vanity address: {}", address);
-    tracing::debug!("Generation completed with seed: {}", seed);
+    let matcher = match args.matcher() {
+        Ok(matcher) => matcher,
+        Err(e) => {
+            tracing::error!("Invalid matcher provided: {}", e);
+            let reason = match e {
+                GrindError::InvalidRegex(_) => Reason::InvalidRegex,
+                GrindError::InvalidLeadingZeros(_) => Reason::InvalidLeadingZeros,
+                GrindError::InvalidReference(_) => Reason::InvalidReference,
+                GrindError::InvalidByteSum { .. } => Reason::InvalidByteSum,
+                GrindError::InvalidPattern(_) => Reason::InvalidPattern,
+                GrindError::InvalidLookalike(_) => Reason::InvalidLookalike,
+                _ => Reason::InvalidTargetChar,
+            };
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, reason, e.to_string()));
+        }
+    };
+    if let Err(e) = args.seed_affixes() {
+        tracing::error!("Invalid seed affixes provided: {}", e);
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, Reason::InvalidSeed, e.to_string()));
+    }
+    // Rather than grind until the timeout for a match that can't be had
+    if let Err(e) = args.check_seed_space(&matcher, 1) {
+        tracing::error!("Infeasible target provided: {}", e);
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            Reason::InfeasibleTarget,
+            format!("Target is infeasible: {e}"),
+        ));
+    }
+    tracing::debug!("GrindArgs configured with matcher: {:?}", args.matcher);
+    Ok(args)
+}
 
-    Ok(Json(GenerateResponse {
-        address: address.to_string(),
+/// The base58 address and seed are always included, the rest are opt-in.
+/// `stats` are the attempts and time taken, if known, and `reporter` is
+/// the request's [`ResponseOptions::rule_reporter`].
+fn generate_response(
+    options: &ResponseOptions,
+    seed: String,
+    address: Pubkey,
+    config: &GrindConfig,
+    stats: Option<(u64, Duration)>,
+    reporter: Option<&Matcher>,
+) -> GenerateResponse {
+    let bytes = address.to_bytes();
+    let wants = |encoding| !options.minimal && options.encodings.contains(&encoding);
+    let case_comparison = (options.compare_case && !options.minimal)
+        .then(|| CaseComparison::new(&config.matcher, stats.map(|(attempts, _)| attempts)))
+        .flatten();
+    let stats = stats.filter(|_| options.details && !options.minimal);
+    let address = address.to_string();
+    GenerateResponse {
+        matched_lower: (config.matcher.case_insensitive() && !options.minimal)
+            .then(|| maybe_bs58_aware_lowercase(&address, true)),
+        matched_rules: reporter.map(|matcher| matcher.matched_rules(&bytes)),
+        display: options
+            .display
+            .filter(|_| !options.minimal)
+            .map(|display| short_address(&address, display.head, display.tail)),
+        address,
         seed,
+        address_hex: wants(Encoding::Hex).then(|| to_hex(&bytes)),
+        address_base64: wants(Encoding::Base64)
+            .then(|| base64::engine::general_purpose::STANDARD.encode(bytes)),
+        attempts: stats.map(|(attempts, _)| attempts),
+        elapsed_ms: stats.map(|(_, elapsed)| elapsed.as_millis() as u64),
+        config: (options.echo_config && !options.minimal).then(|| config.clone()),
+        cached: false,
+        attestation: None,
+        metadata: (options.metadata && !options.minimal).then(Metadata::now),
+        case_comparison,
+    }
+}
+
+/// Signs a result with the server's keypair, if it has one. Cached
+/// results get a fresh timestamp, as that's when this server vouched for
+/// them.
+fn attest(
+    state: &AppState,
+    address: &Pubkey,
+    seed: &str,
+    base: &Pubkey,
+    owner: &Pubkey,
+) -> Option<Attestation> {
+    let keypair = state.attestation_keypair.as_ref()?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let message = attestation_message(address, seed, base, owner, timestamp);
+    Some(Attestation {
+        timestamp,
+        signature: keypair.sign_message(&message).to_string(),
+        server_pubkey: keypair.pubkey().to_string(),
+    })
+}
+
+/// Runs the grind for `args` on the blocking pool, holding `slot` until
+/// it's done. Dropping the future before then stops the grind.
+fn spawn_grind(args: GrindArgs, slot: Option<OwnedSemaphorePermit>) -> GrindFuture {
+    let cancel = CancellationToken::new();
+    let abandoned = cancel.clone().drop_guard();
+    tokio::task::spawn_blocking(move || {
+        let _slot = slot;
+        grind_with_result(args, &cancel)
+    })
+    .map(move |joined| {
+        abandoned.disarm();
+        joined.unwrap_or_else(|e| Err(GrindError::Worker(e.to_string())))
+    })
+    .boxed()
+}
+
+/// One request's wait on a shared grind. Once the last waiter is gone,
+/// whether the grind finished or its client went away, the grind's entry
+/// is cleared.
+struct Waiting<'a> {
+    state: &'a AppState,
+    key: GrindKey,
+    grind: Option<SharedGrind>,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        // Let go of this waiter's hold first, so it can be the last
+        drop(self.grind.take());
+        let mut in_flight = self.state.in_flight.lock().unwrap();
+        if in_flight
+            .get(&self.key)
+            .is_some_and(|grind| grind.upgrade().is_none())
+        {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+/// Runs the grind for `args` on the blocking pool, sharing it with any
+/// concurrent request for the same key and caching its result for later
+/// ones. A `fresh` grind is never shared or cached.
+///
+/// Only starting a grind takes a slot, so joining one never waits in the
+/// queue; that is the only way to fail before grinding, with a 503.
+async fn shared_grind(
+    state: &Arc<AppState>,
+    args: GrindArgs,
+    fresh: bool,
+) -> Result<Result<Found, GrindError>, ApiError> {
+    if fresh {
+        let slot = grind_slot(state).await?;
+        return Ok(spawn_grind(args, slot).await);
+    }
+
+    let key = GrindKey::new(&args);
+    let running = |in_flight: &HashMap<GrindKey, WeakShared<GrindFuture>>| {
+        let grind = in_flight.get(&key).and_then(WeakShared::upgrade);
+        if grind.is_some() {
+            tracing::info!("Joining an in-flight grind for the same target");
+        }
+        grind
+    };
+    let joined = running(&state.in_flight.lock().unwrap());
+    let grind = match joined {
+        Some(grind) => grind,
+        None => {
+            let slot = grind_slot(state).await?;
+            let mut in_flight = state.in_flight.lock().unwrap();
+            // Someone may have started it while this request was queued,
+            // in which case the slot goes back
+            match running(&in_flight) {
+                Some(grind) => grind,
+                None => {
+                    let grind = spawn_grind(args, slot).shared();
+                    if let Some(weak) = grind.downgrade() {
+                        in_flight.insert(key.clone(), weak);
+                    }
+                    grind
+                }
+            }
+        }
+    };
+
+    let waiting = Waiting {
+        state,
+        key: key.clone(),
+        grind: Some(grind),
+    };
+    // Polling a clone leaves this one to tell the grind apart by
+    let grind = waiting.grind.as_ref().unwrap();
+    let result = grind.clone().await;
+
+    // Whoever finishes first clears the entry, taking care not to evict
+    // a newer grind that was started for the same key in the meantime
+    let mut in_flight = state.in_flight.lock().unwrap();
+    if in_flight
+        .get(&key)
+        .and_then(WeakShared::upgrade)
+        .is_some_and(|current| current.ptr_eq(grind))
+    {
+        in_flight.remove(&key);
+        if let Ok(found) = &result {
+            state.results.lock().unwrap().insert(key, found.clone());
+        }
+    }
+    drop(in_flight);
+
+    Ok(result)
+}
+
+/// Fails with a 401 unless the request bears the admin token
+fn authorize(state: &AppState, headers: &HeaderMap, what: &str) -> Result<(), ApiError> {
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    // The admin routes only exist when a token is configured
+    let expected = state.admin_token.as_deref().unwrap_or_default();
+    if !presented.is_some_and(|token| constant_time_eq(token.as_bytes(), expected.as_bytes())) {
+        tracing::warn!("Rejected unauthorized {} request", what);
+        return Err(ApiError::new(StatusCode::UNAUTHORIZED, Reason::Unauthorized, "Unauthorized"));
+    }
+    Ok(())
+}
+
+/// Starts a graceful shutdown, for callers holding the admin token
+async fn admin_shutdown(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    authorize(&state, &headers, "shutdown")?;
+
+    tracing::info!("Shutdown requested via /admin/shutdown");
+    state.shutdown_requested.notify_one();
+    Ok((StatusCode::ACCEPTED, "shutting down"))
+}
+
+/// The settings the server is actually running with, leaving out the
+/// admin token
+#[derive(Serialize)]
+struct EffectiveConfig {
+    bind: SocketAddr,
+    token_program_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_file: Option<PathBuf>,
+    cores: u32,
+    grind_threads: u32,
+    max_grind_threads: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_concurrent_grinds: Option<usize>,
+    max_queued_grinds: usize,
+    queue_full_retry_after_secs: u64,
+    result_cache_size: usize,
+    /// Who signs attestations, if anyone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attestation_pubkey: Option<String>,
+    header_timeout_secs: u64,
+    body_timeout_secs: u64,
+    request_timeout_secs: u64,
+    grind_timeout_secs: u64,
+    watchdog_multiple: f64,
+    features: Vec<&'static str>,
+}
+
+/// The effective config, for callers holding the admin token
+async fn admin_config(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<EffectiveConfig>, ApiError> {
+    authorize(&state, &headers, "config")?;
+
+    let args = &state.args;
+    // The reloadable ones reflect the --config file as of the last reload
+    let config = state.config();
+    Ok(Json(EffectiveConfig {
+        bind: args.bind,
+        token_program_id: config.token_program_id.to_string(),
+        config_file: args.config.clone(),
+        cores: available_cpus(),
+        grind_threads: args.grind_threads(None),
+        max_grind_threads: args.grind_threads(Some(u32::MAX)),
+        max_concurrent_grinds: args.max_concurrent_grinds,
+        max_queued_grinds: config.max_queued_grinds,
+        queue_full_retry_after_secs: args.queue_full_retry_after_secs,
+        result_cache_size: args.result_cache_size,
+        attestation_pubkey: state
+            .attestation_keypair
+            .as_ref()
+            .map(|keypair| keypair.pubkey().to_string()),
+        header_timeout_secs: args.header_timeout_secs,
+        body_timeout_secs: args.body_timeout_secs,
+        request_timeout_secs: config.request_timeout_secs,
+        grind_timeout_secs: config.grind_timeout_secs,
+        watchdog_multiple: args.watchdog_multiple,
+        features: [
+            cfg!(feature = "gpu").then_some("gpu"),
+            cfg!(feature = "fast-rng").then_some("fast-rng"),
+            cfg!(feature = "webhook").then_some("webhook"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
     }))
 }
 
-pub async fn start_server() {
+/// Compares without short-circuiting, so timing doesn't leak the token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub async fn start_server(args: ServerArgs) -> Result<(), ServerError> {
     // Initialize tracing with more detailed format
     tracing_subscriber::fmt()
         .with_target(true)
@@ -147,42 +1738,746 @@ pub async fn start_server() {
         .init();
 
     tracing::info!("Initializing server...");
-    
+
     // Create app state
-    let state = Arc::new(AppState {
-        token_program_id: Pubkey::try_from("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap(),
-    });
-    tracing::info!("App state initialized with token program ID");
+    if args.max_grind_threads > available_cpus() {
+        tracing::warn!(
+            "--max-grind-threads {} is more than the {} cores available, using {}",
+            args.max_grind_threads,
+            available_cpus(),
+            available_cpus()
+        );
+    }
+    let state = Arc::new(AppState::new(args)?);
+    tracing::info!(
+        "App state initialized with token program ID {}",
+        state.config().token_program_id
+    );
+    if let Some(keypair) = &state.attestation_keypair {
+        tracing::info!("Signing attestations as {}", keypair.pubkey());
+    }
+    #[cfg(unix)]
+    tokio::spawn(reload_on_sighup(state.clone()));
 
-    // Build router
-    let app = Router::new()
+    let app = router(state.clone());
+    tracing::info!("Router configured with health check and generate endpoints");
+
+    // Run server with HTTP/1.1
+    let addr = state.args.bind;
+    tracing::info!("Attempting to bind to address: {}", addr);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("Successfully bound to {}", addr);
+    tracing::info!("Server is ready to accept connections");
+
+    let header_timeout = Duration::from_secs(state.args.header_timeout_secs);
+    serve(listener, app, header_timeout, shutdown_signal(state)).await;
+    tracing::info!("Server shutdown complete");
+    Ok(())
+}
+
+/// Like axum::serve with graceful shutdown, but with a timeout on reading
+/// request headers, which axum doesn't expose, so slow clients can't hold
+/// connections open
+async fn serve(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    header_timeout: Duration,
+    shutdown: impl Future<Output = ()>,
+) {
+    let mut builder = http1::Builder::new();
+    builder
+        .timer(TokioTimer::new())
+        .header_read_timeout(header_timeout);
+    let graceful = GracefulShutdown::new();
+
+    tokio::pin!(shutdown);
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+
+        let service = TowerToHyperService::new(app.clone());
+        let connection = builder.serve_connection(TokioIo::new(stream), service);
+        let connection = graceful.watch(connection);
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::debug!("Connection closed with an error: {}", e);
+            }
+        });
+    }
+
+    // Stop accepting, then let open connections finish their requests
+    drop(listener);
+    graceful.shutdown().await;
+}
+
+impl AppState {
+    /// Validates `args` into fresh state with nothing in flight
+    fn new(args: ServerArgs) -> Result<AppState, ServerError> {
+        let config = ServerConfig::load(&args)?;
+        Blocklist::new(&args.avoid_lookalikes).map_err(|e| ServerError::Config(e.to_string()))?;
+        let attestation_keypair = args
+            .attestation_keypair
+            .as_ref()
+            .map(|path| {
+                read_keypair_file(path)
+                    .map_err(|e| ServerError::AttestationKeypair(format!("{}: {e}", path.display())))
+            })
+            .transpose()?;
+
+        Ok(AppState {
+            config: RwLock::new(Arc::new(config)),
+            in_flight: Mutex::new(HashMap::new()),
+            results: Mutex::new(LruCache::new(args.result_cache_size)),
+            requests_in_flight: AtomicUsize::new(0),
+            drained: Notify::new(),
+            shutting_down: AtomicBool::new(false),
+            shutdown_requested: Notify::new(),
+            admin_token: args.admin_token.clone(),
+            attestation_keypair,
+            metrics: Metrics::default(),
+            jobs: Mutex::new(HashMap::new()),
+            grind_slots: args.max_concurrent_grinds.map(|n| Arc::new(Semaphore::new(n))),
+            queued: AtomicUsize::new(0),
+            args,
+        })
+    }
+
+    /// The current config, which stays valid even if a reload replaces it
+    fn config(&self) -> Arc<ServerConfig> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Swaps in a freshly loaded config, keeping the current one if the
+    /// new one is invalid
+    fn reload(&self) -> Result<(), ServerError> {
+        let config = ServerConfig::load(&self.args)?;
+        *self.config.write().unwrap() = Arc::new(config);
+        Ok(())
+    }
+}
+
+/// Reloads the config whenever the process gets a SIGHUP
+#[cfg(unix)]
+async fn reload_on_sighup(state: Arc<AppState>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::error!("Failed to install SIGHUP handler: {}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        match state.reload() {
+            Ok(()) => tracing::info!("Reloaded config: {:?}", state.config()),
+            Err(e) => tracing::error!("Failed to reload config, keeping the current one: {}", e),
+        }
+    }
+}
+
+/// The whole API over `state`, without binding anything, so it can also
+/// be driven in-process (e.g. with tower::ServiceExt::oneshot)
+fn router(state: Arc<AppState>) -> Router {
+    let args = &state.args;
+    let mut app = Router::new()
         .route("/health", get(health_check))
+        .route("/metrics", get(metrics))
+        .route("/generate/:id/cancel", post(cancel_job))
+        .route("/generate/:id/status", get(job_status));
+    if state.admin_token.is_some() {
+        app = app
+            .route("/admin/shutdown", post(admin_shutdown))
+            .route("/config", get(admin_config));
+        tracing::info!("Admin endpoints enabled");
+    }
+
+    // Layers only wrap the routes added before them, so the grinds are
+    // added after the request timeout
+    app.layer(middleware::from_fn_with_state(state.clone(), request_timeout))
         .route("/generate", post(generate_vanity_address))
+        .route("/generate/stream", post(generate_vanity_address_stream))
+        .layer(RequestBodyTimeoutLayer::new(Duration::from_secs(args.body_timeout_secs)))
+        .layer(middleware::from_fn_with_state(state.clone(), count_rejections))
         .with_state(state)
         .layer(
             CorsLayer::new()
                 .allow_origin(tower_http::cors::Any)
                 .allow_methods(tower_http::cors::Any)
                 .allow_headers(tower_http::cors::Any),
-        );
-    tracing::info!("Router configured with health check and generate endpoints");
+        )
+}
 
-    // Run server with HTTP/1.1
-    let addr = "0.0.0.0:3001";
-    tracing::info!("Attempting to bind to address: {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    tracing::info!("Successfully bound to {}", addr);
-    tracing::info!("Server is ready to accept connections");
-    
-    axum::serve(listener, app.into_make_service())
-        .with_graceful_shutdown(shutdown_signal())
-        .await
-        .unwrap();
-    tracing::info!("Server shutdown complete");
+/// Resolves on CTRL+C or /admin/shutdown, once in-flight requests drain
+async fn shutdown_signal(state: Arc<AppState>) {
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result.expect("Failed to install CTRL+C signal handler");
+        }
+        _ = state.shutdown_requested.notified() => {}
+    }
+
+    state.shutting_down.store(true, Ordering::Release);
+    tracing::info!("Shutting down, waiting for in-flight requests");
+    loop {
+        // Registered before checking so a drop in between isn't missed
+        let drained = state.drained.notified();
+        if state.requests_in_flight.load(Ordering::Acquire) == 0 {
+            break;
+        }
+        drained.await;
+    }
 }
 
-async fn shutdown_signal() {
-    tokio::signal::ctrl_c()
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::routing;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const BASE: &str = "11111111111111111111111111111111";
+
+    /// Server args as if started with just `flags`
+    fn test_args(flags: &[&str]) -> ServerArgs {
+        ServerArgs::parse_from(std::iter::once("vanity-server").chain(flags.iter().copied()))
+    }
+
+    /// The router over fresh state for `args`, and the state to inspect
+    fn test_app(args: ServerArgs) -> (Router, Arc<AppState>) {
+        let state = Arc::new(AppState::new(args).unwrap());
+        (router(state.clone()), state)
+    }
+
+    fn get(uri: &str) -> Request {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    fn post(uri: &str, body: impl Into<Body>) -> Request {
+        Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.into())
+            .unwrap()
+    }
+
+    /// Sends `request` through `app` in-process, returning the status and
+    /// body
+    async fn send(app: &Router, request: Request) -> (StatusCode, String) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    /// Posts `body` as json, returning the status and json response
+    async fn post_json(app: &Router, uri: &str, body: Value) -> (StatusCode, Value) {
+        let (status, body) = send(app, post(uri, body.to_string())).await;
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn health_is_ok() {
+        let (app, _) = test_app(test_args(&[]));
+        assert_eq!(send(&app, get("/health")).await, (StatusCode::OK, "ok".to_string()));
+    }
+
+    #[tokio::test]
+    async fn generate_matches_the_suffix() {
+        let (app, _) = test_app(test_args(&[]));
+        let (status, body) =
+            post_json(&app, "/generate", json!({ "base": BASE, "suffix": "a" })).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert!(body["address"].as_str().unwrap().ends_with('a'), "{body}");
+    }
+
+    #[tokio::test]
+    async fn generate_reproducibly_matches_the_prefix() {
+        let (app, _) = test_app(test_args(&[]));
+        let (status, body) =
+            post_json(&app, "/generate", json!({ "base": BASE, "prefix": "V" })).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let address = body["address"].as_str().unwrap();
+        assert!(address.starts_with('V'), "{body}");
+
+        // The default owner, with the seed returned, gives the same address
+        let base = Pubkey::try_from(BASE).unwrap();
+        let seed = body["seed"].as_str().unwrap();
+        let derived = derive_address(&base, seed.as_bytes(), &owners::TOKEN_PROGRAM, false);
+        assert_eq!(derived.unwrap().to_string(), address);
+    }
+
+    #[tokio::test]
+    async fn failed_requests_leave_nothing_in_flight() {
+        let (app, state) = test_app(test_args(&[]));
+        // Each fails after the handler has counted itself in flight
+        for body in [
+            json!({ "base": "not a pubkey" }),
+            json!({ "base": BASE, "suffix": "0OIl" }),
+            json!({ "base": BASE, "suffix": "a", "last4": "abcd" }),
+        ] {
+            let (status, _) = post_json(&app, "/generate", body).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(state.requests_in_flight.load(Ordering::Acquire), 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_a_422_error_response() {
+        let (app, _) = test_app(test_args(&[]));
+        for (body, mentions) in [
+            ("{\"base\": ", "EOF"),
+            ("{\"suffix\": \"a\"}", "base"),
+            ("{\"base\": 1}", "base"),
+        ] {
+            let (status, body) = send(&app, post("/generate", body)).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{body}");
+            let body: Value = serde_json::from_str(&body).unwrap();
+            let error = body["error"].as_str().unwrap();
+            assert!(error.contains(mentions), "{error}");
+            assert_eq!(body.as_object().unwrap().len(), 1, "{body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn generate_combines_matcher_rules() {
+        let (app, _) = test_app(test_args(&[]));
+        let matcher = json!({
+            "combine": "all",
+            "rules": [
+                { "prefix": ["a", "b"], "case_insensitive": true },
+                { "suffix": "c" },
+            ],
+        });
+        let (status, body) =
+            post_json(&app, "/generate", json!({ "base": BASE, "matcher": matcher })).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let address = body["address"].as_str().unwrap();
+        assert!(address.to_lowercase().starts_with(['a', 'b']), "{address}");
+        assert!(address.ends_with('c'), "{address}");
+        assert!(body.get("matched_rules").is_none(), "{body}");
+
+        // Every rule matched, so every rule is reported, with the prefix
+        // that matched
+        let request = json!({ "base": BASE, "matcher": matcher, "matched_rules": true });
+        let (status, body) = post_json(&app, "/generate", request).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let address = body["address"].as_str().unwrap();
+        let prefix = address[..1].to_lowercase();
+        let expected = json!([
+            { "prefix": [prefix], "case_insensitive": true },
+            { "suffix": "c", "case_insensitive": false },
+        ]);
+        assert_eq!(body["matched_rules"], expected);
+    }
+
+    #[tokio::test]
+    async fn generate_rejects_conflicting_and_infeasible_targets() {
+        let (app, _) = test_app(test_args(&[]));
+        let matcher = json!({ "rules": [{ "suffix": "c" }] });
+        for (body, mentions) in [
+            (json!({ "base": BASE, "matcher": matcher, "prefix": "a" }), "not both"),
+            (json!({ "base": BASE, "matcher": { "rules": [{ "regex": "(" }] } }), "regex"),
+            (json!({ "base": BASE, "leading_zeros": 20 }), "infeasible"),
+        ] {
+            let (status, body) = post_json(&app, "/generate", body).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+            let error = body["error"].as_str().unwrap();
+            assert!(error.contains(mentions), "{error}");
+        }
+    }
+
+    #[tokio::test]
+    async fn abandoned_grinds_are_cancelled() {
+        let (app, state) = test_app(test_args(&["--max-concurrent-grinds", "1"]));
+        let slots = state.grind_slots.clone().unwrap();
+        // A regex isn't estimated, so nothing stops this grind but its
+        // client going away
+        let never = json!({ "base": BASE, "matcher": { "rules": [{ "regex": "^$" }] } });
+        let client = tokio::spawn({
+            let app = app.clone();
+            async move { send(&app, post("/generate", never.to_string())).await }
+        });
+        while slots.available_permits() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        client.abort();
+        let released = async {
+            while slots.available_permits() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), released)
+            .await
+            .expect("the abandoned grind kept its slot");
+        assert!(state.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reload_applies_the_config_file() {
+        let path = std::env::temp_dir()
+            .join(format!("vanity-server-config-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"max_queued_grinds": 4}"#).unwrap();
+        let config = path.to_str().unwrap();
+        let flags = ["--config", config, "--admin-token", "secret", "--max-concurrent-grinds", "1"];
+        let (app, state) = test_app(test_args(&flags));
+        assert_eq!(state.config().max_queued_grinds, 4);
+        assert_eq!(state.config().token_program_id, owners::TOKEN_PROGRAM);
+
+        std::fs::write(
+            &path,
+            r#"{
+                "token_program_id": "token-2022",
+                "grind_timeout_secs": 7,
+                "request_timeout_secs": 5,
+                "max_queued_grinds": 0
+            }"#,
+        )
+        .unwrap();
+        state.reload().unwrap();
+        let request = Request::get("/config")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(&app, request).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["token_program_id"], owners::TOKEN_2022_PROGRAM.to_string());
+        assert_eq!(body["grind_timeout_secs"], 7);
+        assert_eq!(body["request_timeout_secs"], 5);
+        assert_eq!(body["max_queued_grinds"], 0);
+
+        // With the only slot taken and no queue, the next grind is refused
+        let slots = state.grind_slots.clone().unwrap();
+        let _taken = slots.try_acquire_owned().unwrap();
+        let (status, body) =
+            post_json(&app, "/generate", json!({ "base": BASE, "suffix": "a" })).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+
+        // A bad file keeps the config as it was
+        std::fs::write(&path, r#"{"max_queued_grinds": -1}"#).unwrap();
+        assert!(state.reload().is_err());
+        assert_eq!(state.config().max_queued_grinds, 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// A matcher nothing matches: a regex isn't estimated, so it passes
+    /// the seed space check, and no address is empty
+    fn never() -> Value {
+        json!({ "base": BASE, "matcher": { "rules": [{ "regex": "^$" }] } })
+    }
+
+    #[tokio::test]
+    async fn impossible_targets_time_out() {
+        let (app, _) = test_app(test_args(&["--grind-timeout-secs", "1"]));
+        let (status, body) = tokio::time::timeout(
+            Duration::from_secs(30),
+            post_json(&app, "/generate", never()),
+        )
+        .await
+        .expect("the grind outlived its timeout");
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+        assert!(body["error"].as_str().unwrap().contains("1s"), "{body}");
+    }
+
+    /// The job's attempts from /generate/{id}/status, or None once the job
+    /// is gone
+    async fn job_attempts(app: &Router, id: &str) -> Option<u64> {
+        let (status, body) = send(app, get(&format!("/generate/{id}/status"))).await;
+        match status {
+            StatusCode::NOT_FOUND => None,
+            _ => {
+                let body: Value = serde_json::from_str(&body).unwrap();
+                Some(body["attempts"].as_u64().unwrap())
+            }
+        }
+    }
+
+    /// The job id from the started event a stream begins with
+    async fn started_job(body: &mut axum::body::BodyDataStream) -> String {
+        let started = body.next().await.unwrap().unwrap();
+        let started = std::str::from_utf8(&started).unwrap();
+        let data = started.lines().find_map(|line| line.strip_prefix("data: "));
+        let started: Value = serde_json::from_str(data.unwrap()).unwrap();
+        started["job_id"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn job_status_advances_without_reading_the_stream() {
+        let (app, _) = test_app(test_args(&[]));
+        let response =
+            app.clone().oneshot(post("/generate/stream", never().to_string())).await.unwrap();
+        let mut body = response.into_body().into_data_stream();
+        let id = started_job(&mut body).await;
+
+        let advancing = async {
+            let mut seen = 0;
+            for _ in 0..2 {
+                while job_attempts(&app, &id).await.unwrap() == seen {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                seen = job_attempts(&app, &id).await.unwrap();
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(30), advancing)
+            .await
+            .expect("the job's attempts never advanced");
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_job_stops_its_attempts() {
+        let (app, state) = test_app(test_args(&[]));
+        let response =
+            app.clone().oneshot(post("/generate/stream", never().to_string())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body().into_data_stream();
+        let id = started_job(&mut body).await;
+
+        // The workers' own count, which they hold on to until they stop
+        let progress = state.jobs.lock().unwrap()[&id].progress.clone();
+        let attempts = || progress.lock().unwrap().attempts;
+        let advancing = async {
+            while attempts() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(30), advancing)
+            .await
+            .expect("the job's attempts never advanced");
+
+        let (status, text) = send(&app, post(&format!("/generate/{id}/cancel"), "")).await;
+        assert_eq!(status, StatusCode::OK, "{text}");
+        let mut events = String::new();
+        let read = async {
+            while let Some(chunk) = body.next().await {
+                events.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(30), read)
+            .await
+            .expect("the stream outlived its cancellation");
+        assert!(events.contains("event: cancelled"), "{events}");
+        drop(body);
+        assert_eq!(job_attempts(&app, &id).await, None);
+
+        // Everything else holding the count went with the stream
+        let stopped = async {
+            while Arc::strong_count(&progress) > 1 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), stopped)
+            .await
+            .expect("the workers kept grinding after the job was cancelled");
+        let last = attempts();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(attempts(), last);
+    }
+
+    #[test]
+    fn grinds_use_the_watchdog_multiple() {
+        let state = AppState::new(test_args(&["--watchdog-multiple", "3"])).unwrap();
+        let req = serde_json::from_value(json!({ "base": BASE, "suffix": "c" })).unwrap();
+        let Ok(args) = grind_args(&state, &req) else {
+            panic!("the request is valid");
+        };
+        assert_eq!(args.watchdog_multiple, 3.0);
+    }
+
+    #[test]
+    fn equivalent_requests_share_a_key() {
+        let state = AppState::new(test_args(&[])).unwrap();
+        let key = |rule: Value| {
+            let matcher = json!({ "rules": [rule] });
+            let req = serde_json::from_value(json!({ "base": BASE, "matcher": matcher })).unwrap();
+            let Ok(args) = grind_args(&state, &req) else {
+                panic!("the request is valid");
+            };
+            GrindKey::new(&args)
+        };
+
+        assert!(
+            key(json!({ "prefix": "Abc", "case_insensitive": true }))
+                == key(json!({ "prefix": "abc", "case_insensitive": true }))
+        );
+        assert!(
+            key(json!({ "prefix": ["ab", "Cd", "ab"] })) == key(json!({ "prefix": ["Cd", "ab"] }))
+        );
+        assert!(
+            key(json!({ "suffix": "Zoo", "case_insensitive": true }))
+                == key(json!({ "suffix": "zoo", "case_insensitive": true }))
+        );
+        // Case still matters to case-sensitive rules
+        assert!(key(json!({ "prefix": "Abc" })) != key(json!({ "prefix": "abc" })));
+    }
+
+    #[tokio::test]
+    async fn a_zero_request_timeout_is_no_limit() {
+        for (secs, expected) in [("0", StatusCode::OK), ("1", StatusCode::REQUEST_TIMEOUT)] {
+            let state = AppState::new(test_args(&["--request-timeout-secs", secs])).unwrap();
+            let slow = || async {
+                tokio::time::sleep(Duration::from_millis(1100)).await;
+                "done"
+            };
+            let app = Router::new()
+                .route("/slow", routing::get(slow))
+                .layer(middleware::from_fn_with_state(Arc::new(state), request_timeout));
+            let (status, body) = send(&app, get("/slow")).await;
+            assert_eq!(status, expected, "{body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicates_join_a_running_grind_without_a_slot() {
+        let flags = ["--max-concurrent-grinds", "1", "--max-queued-grinds", "0"];
+        let (app, state) = test_app(test_args(&flags));
+        let slots = state.grind_slots.clone().unwrap();
+        let request = |app: &Router| {
+            let app = app.clone();
+            tokio::spawn(async move { send(&app, post("/generate", never().to_string())).await })
+        };
+        let first = request(&app);
+        while slots.available_permits() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Another target needs a slot, with no room to queue for one
+        let (status, body) =
+            post_json(&app, "/generate", json!({ "base": BASE, "suffix": "a" })).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+        // The same target joins the grind instead, and waits on it
+        let duplicate = request(&app);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!duplicate.is_finished());
+        assert_eq!(state.in_flight.lock().unwrap().len(), 1);
+
+        first.abort();
+        duplicate.abort();
+        let released = async {
+            while slots.available_permits() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), released)
+            .await
+            .expect("the abandoned grind kept its slot");
+    }
+
+    #[tokio::test]
+    async fn queued_streams_can_be_cancelled() {
+        let (app, state) = test_app(test_args(&["--max-concurrent-grinds", "1"]));
+        let _taken = state.grind_slots.clone().unwrap().try_acquire_owned().unwrap();
+        let stream = app.clone().oneshot(post("/generate/stream", never().to_string()));
+        let response = tokio::time::timeout(Duration::from_secs(10), stream)
+            .await
+            .expect("the stream waited for a slot before starting")
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The job id comes out while the stream waits for a slot
+        let mut body = response.into_body().into_data_stream();
+        let id = started_job(&mut body).await;
+        assert_eq!(state.queued.load(Ordering::Relaxed), 1);
+        assert_eq!(job_attempts(&app, &id).await, Some(0));
+
+        let (status, body_text) = send(&app, post(&format!("/generate/{id}/cancel"), "")).await;
+        assert_eq!(status, StatusCode::OK, "{body_text}");
+        let mut events = String::new();
+        while let Some(chunk) = body.next().await {
+            events.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+        }
+        assert!(events.contains("event: cancelled"), "{events}");
+        let dequeued = async {
+            while state.queued.load(Ordering::Relaxed) > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), dequeued)
+            .await
+            .expect("the cancelled stream stayed queued");
+    }
+
+    #[cfg(feature = "webhook")]
+    #[tokio::test]
+    async fn callbacks_are_accepted_while_grinds_queue() {
+        let flags = ["--max-concurrent-grinds", "1", "--webhook-hosts", "example.com"];
+        let (app, state) = test_app(test_args(&flags));
+        let _taken = state.grind_slots.clone().unwrap().try_acquire_owned().unwrap();
+        let request = json!({
+            "base": BASE,
+            "suffix": "a",
+            "callback_url": "https://example.com/found",
+        });
+        let (status, body) = tokio::time::timeout(
+            Duration::from_secs(10),
+            post_json(&app, "/generate", request),
+        )
         .await
-        .expect("Failed to install CTRL+C signal handler");
+        .expect("the callback request waited for a slot");
+        assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+
+        let queued = async {
+            while state.queued.load(Ordering::Relaxed) == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), queued)
+            .await
+            .expect("the callback's grind never queued");
+    }
+
+    #[tokio::test]
+    async fn display_survives_huge_lengths() {
+        let (app, _) = test_app(test_args(&[]));
+        let display = json!({ "head": usize::MAX, "tail": 1 });
+        let request = json!({ "base": BASE, "suffix": "a", "display": display });
+        let (status, body) = post_json(&app, "/generate", request).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["display"], body["address"]);
+    }
+
+    struct NoEntropy;
+
+    impl rand::RngCore for NoEntropy {
+        fn next_u32(&mut self) -> u32 {
+            panic!("asked for entropy without a way to fail");
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            panic!("asked for entropy without a way to fail");
+        }
+
+        fn fill_bytes(&mut self, _dest: &mut [u8]) {
+            panic!("asked for entropy without a way to fail");
+        }
+
+        fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand::Error> {
+            Err(rand::Error::new("no entropy here"))
+        }
+    }
+
+    #[test]
+    fn jobs_without_entropy_are_a_500() {
+        let state = Arc::new(AppState::new(test_args(&[])).unwrap());
+        let Err(e) = Job::start_from(state.clone(), &mut NoEntropy) else {
+            panic!("started a job without entropy");
+        };
+        assert_eq!(e.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(e.error.contains("no entropy here"), "{}", e.error);
+        assert!(state.jobs.lock().unwrap().is_empty());
+
+        let job = Job::start(state.clone()).unwrap_or_else(|_| panic!("no os entropy"));
+        assert_eq!(job.id.len(), 32);
+        assert!(state.jobs.lock().unwrap().contains_key(&job.id));
+    }
 }
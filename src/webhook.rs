@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use reqwest::{redirect, Client, Url};
+use serde::Serialize;
+
+use crate::metadata::Metadata;
+
+/// Deliveries tried before giving up on a callback
+const MAX_ATTEMPTS: u32 = 3;
+
+/// How long a callback gets to answer each delivery
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a callback is POSTed once its grind succeeds
+#[derive(Serialize)]
+pub struct WebhookPayload {
+    pub address: String,
+    pub seed: String,
+    pub attempts: u64,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+/// Parses a callback url, which must be https (or http if `allow_http`)
+/// and exactly one of `allowed_hosts`, so requests can't point the server
+/// at anything the operator didn't list
+pub fn validate(url: &str, allowed_hosts: &[String], allow_http: bool) -> Result<Url, String> {
+    let url = Url::parse(url).map_err(|e| format!("invalid callback_url: {e}"))?;
+    match url.scheme() {
+        "https" => {}
+        "http" if allow_http => {}
+        scheme => return Err(format!("callback_url scheme {scheme} is not allowed")),
+    }
+    let host = url.host_str().unwrap_or_default();
+    if !allowed_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    {
+        return Err(format!("callback_url host {host} is not allowed"));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err("callback_url must not have credentials".to_string());
+    }
+    Ok(url)
+}
+
+/// POSTs `payload` to `url`, retrying with backoff until it answers with
+/// a 2xx or [`MAX_ATTEMPTS`] run out. Redirects aren't followed, since
+/// they could lead off the allowlist.
+pub async fn deliver(url: Url, payload: WebhookPayload) {
+    let client = match Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to build webhook client: {}", e);
+            return;
+        }
+    };
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        match client.post(url.clone()).json(&payload).send().await {
+            Ok(response) if response.status().is_success() => {
+                tracing::info!("Delivered {} to {}", payload.address, url);
+                return;
+            }
+            Ok(response) => {
+                tracing::warn!(
+                    "Webhook {} answered {} (attempt {})",
+                    url,
+                    response.status(),
+                    attempt
+                );
+            }
+            Err(e) => tracing::warn!("Webhook {} failed: {} (attempt {})", url, e, attempt),
+        }
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+        }
+    }
+    tracing::error!(
+        "Gave up delivering {} (seed {}) to {}",
+        payload.address,
+        payload.seed,
+        url
+    );
+}